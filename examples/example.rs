@@ -13,8 +13,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
   stdout.flush()?;
   stdin.read_line(&mut password)?;
   let t = LearnHelper::login(username.trim(), password.trim()).await?;
-  let ss = t.semester_id_list().await?;
-  let cs = t.course_list(&ss[0]).await?;
+  let s = t.current_semester().await?;
+  let cs = t.course_list(&s.semester.into()).await?;
   println!("{:#?}", cs);
   t.logout().await?;
   Ok(())
@@ -0,0 +1,419 @@
+//! A command line interface to web-learning, need `features = ["cli"]` to build.
+//!
+//! The username and password are read from (in order):
+//! 1. environment variables `THU_LEARN_USERNAME` and `THU_LEARN_PASSWORD`
+//! 2. the config file, `config.json` in the config directory, or the file given by `--config`,
+//! with the fields `username` and `password`, or `password_file`, which is a file containing only the password
+//! (for example, the one that a keyring tool exports)
+//! 3. the terminal
+//!
+//! The config directory is `$THU_LEARN_CONFIG_DIR`, or `thu-learn` in the platform's config directory.
+//! The login session is saved there as `session.json`, so that the following commands don't need to login again.
+
+use chrono::NaiveDateTime;
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+use std::{env, fs, io::{self, BufRead, Write}, path::{Path, PathBuf}, sync::Arc};
+use structopt::StructOpt;
+use unicode_width::UnicodeWidthStr;
+use thu_learn_helper::{Hosts, Credentials, SessionState, DELETE_DR_DEADLINE, blocking::LearnHelper, render::Render, types::*};
+
+#[derive(StructOpt)]
+#[structopt(name = "thu-learn", about = "A command line interface to web-learning of Tsinghua University.")]
+struct Opt {
+  /// Print json instead of a table.
+  #[structopt(long, global = true)]
+  json: bool,
+  /// The semester that courses are looked up in, for example, "2019-2020-2". It is the current semester by default.
+  #[structopt(long, global = true)]
+  semester: Option<SemesterId>,
+  /// The config file, `config.json` in the config directory by default.
+  #[structopt(long, global = true, parse(from_os_str))]
+  config: Option<PathBuf>,
+  #[structopt(subcommand)]
+  cmd: Cmd,
+}
+
+// `course` can be the name, the english name, the course number or the id of a course
+#[derive(StructOpt)]
+enum Cmd {
+  /// List the semesters, the first one is the current semester.
+  Semesters,
+  /// List the courses of the semester.
+  Courses,
+  /// List the notifications of a course.
+  Notifications { course: String },
+  /// List the files of a course.
+  Files { course: String },
+  /// List the homework of a course.
+  Homework { course: String },
+  /// Submit a homework. `student_homework` is the `student_homework_id` column of `homework`.
+  Submit {
+    student_homework: StudentHomeworkId,
+    /// The content of the submission.
+    #[structopt(long, default_value = "")]
+    content: String,
+    /// The attachment of the submission.
+    #[structopt(long, parse(from_os_str))]
+    file: Option<PathBuf>,
+  },
+  /// List the discussions of a course.
+  Discussions { course: String },
+  /// List the questions in the Q&A board of a course.
+  Questions { course: String },
+  /// Show a question and its answers. `question` is the `id` column of `questions`.
+  Question { course: String, question: QuestionId },
+  /// Ask a question in the Q&A board of a course.
+  Ask {
+    course: String,
+    title: String,
+    /// The content of the question.
+    #[structopt(long)]
+    content: String,
+    /// The attachment of the question.
+    #[structopt(long, parse(from_os_str))]
+    file: Option<PathBuf>,
+  },
+  /// List the discussion boards of a course.
+  Boards { course: String },
+  /// Create a discussion in a course. `board` is the `id` column of `boards`.
+  Post {
+    course: String,
+    board: BoardId,
+    title: String,
+    /// The content of the discussion.
+    #[structopt(long)]
+    content: String,
+    /// The attachment of the discussion.
+    #[structopt(long, parse(from_os_str))]
+    file: Option<PathBuf>,
+  },
+  /// Replace the title and the content of a discussion that you published.
+  EditDiscussion {
+    course: String,
+    discussion: DiscussionId,
+    title: String,
+    /// The content of the discussion.
+    #[structopt(long)]
+    content: String,
+    /// The attachment of the discussion.
+    #[structopt(long, parse(from_os_str))]
+    file: Option<PathBuf>,
+  },
+  /// Delete a discussion that you published.
+  DeleteDiscussion { course: String, discussion: DiscussionId },
+  /// List the replies of a discussion. `discussion` is the `id` column of `discussions`.
+  Replies { course: String, discussion: DiscussionId },
+  /// Reply to a discussion.
+  Reply {
+    course: String,
+    discussion: DiscussionId,
+    /// The content of the reply.
+    #[structopt(long)]
+    content: String,
+    /// Reply to this reply, instead of the discussion.
+    #[structopt(long)]
+    to: Option<ReplyId>,
+    /// The attachment of the reply.
+    #[structopt(long, parse(from_os_str))]
+    file: Option<PathBuf>,
+  },
+  /// Replace the content of a reply that you published, the `mine` column of `replies` is true for such a reply.
+  EditReply {
+    course: String,
+    discussion: DiscussionId,
+    reply: ReplyId,
+    /// The new content of the reply.
+    #[structopt(long)]
+    content: String,
+    /// The attachment of the reply.
+    #[structopt(long, parse(from_os_str))]
+    file: Option<PathBuf>,
+  },
+  /// Delete a reply that you published.
+  DeleteReply { course: String, discussion: DiscussionId, reply: ReplyId },
+  /// Download a file of a course, `file` can be the title or the id of the file. An incomplete download is resumed.
+  Download {
+    course: String,
+    file: String,
+    /// Where to save the file, it is the file name given by the server in the current directory by default.
+    #[structopt(long, parse(from_os_str))]
+    output: Option<PathBuf>,
+  },
+  /// Mark all the notifications and files of a course as read.
+  MarkRead { course: String },
+}
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Deserialize, Default, Clone)]
+struct Config {
+  username: Option<String>,
+  password: Option<String>,
+  password_file: Option<PathBuf>,
+  /// Mainly for testing, see `Hosts`.
+  hosts: Option<Hosts>,
+}
+
+fn config_dir() -> PathBuf {
+  if let Some(x) = env::var_os("THU_LEARN_CONFIG_DIR") { return x.into(); }
+  let base = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+    .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+    .or_else(|| env::var_os("HOME").map(|x| Path::new(&x).join(".config")))
+    .unwrap_or_default();
+  base.join("thu-learn")
+}
+
+// the username and password in the environment variables or the config file, `None` if not found
+fn stored_credentials(config: &Config) -> io::Result<Option<(String, String)>> {
+  if let (Ok(username), Ok(password)) = (env::var("THU_LEARN_USERNAME"), env::var("THU_LEARN_PASSWORD")) {
+    return Ok(Some((username, password)));
+  }
+  let username = match &config.username { Some(x) => x.clone(), None => return Ok(None) };
+  let password = match (&config.password, &config.password_file) {
+    (Some(x), _) => x.clone(),
+    (None, Some(file)) => fs::read_to_string(file)?.lines().next().unwrap_or("").to_owned(),
+    (None, None) => return Ok(None),
+  };
+  Ok(Some((username, password)))
+}
+
+fn prompt_credentials() -> io::Result<(String, String)> {
+  let mut username = String::new();
+  eprint!("Username: ");
+  io::stderr().flush()?;
+  io::stdin().lock().read_line(&mut username)?;
+  let password = rpassword::read_password_from_tty(Some("Password: "))?;
+  Ok((username.trim().to_owned(), password))
+}
+
+fn credentials(config: &Config) -> Result<(String, String)> {
+  Ok(match stored_credentials(config)? { Some(x) => x, None => prompt_credentials()? })
+}
+
+// reuse the saved session if it is still valid, otherwise login
+fn login(dir: &Path, config: &Config) -> Result<LearnHelper> {
+  let hosts = config.hosts.clone().unwrap_or_default();
+  let stored = stored_credentials(config)?;
+  let state = fs::read_to_string(dir.join("session.json")).ok().and_then(|x| serde_json::from_str::<SessionState>(&x).ok());
+  if let Some(state) = state.filter(|x| x.hosts.learn == hosts.learn) {
+    let mut t = LearnHelper::restore_session(state)?;
+    if t.is_session_valid()? {
+      t.credentials = stored.map(|(username, password)| Credentials::Stored { username, password });
+      return Ok(t);
+    }
+  }
+  let (username, password) = match stored { Some(x) => x, None => credentials(config)? };
+  Ok(LearnHelper::login_with(hosts, Credentials::Stored { username, password })?)
+}
+
+fn save_session(dir: &Path, t: &LearnHelper) -> Result<()> {
+  fs::create_dir_all(dir)?;
+  let mut options = fs::OpenOptions::new();
+  options.write(true).create(true).truncate(true);
+  #[cfg(unix)] {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600); // it is as sensitive as the password, so it is never readable by others, even for a moment
+  }
+  Ok(options.open(dir.join("session.json"))?.write_all(serde_json::to_string(&t.save_session())?.as_bytes())?)
+}
+
+// print `items` as json, or as a table with the given columns
+fn print<T: Serialize>(opt: &Opt, items: &[T], columns: &[&str], row: impl Fn(&T) -> Vec<String>) {
+  if opt.json {
+    println!("{}", serde_json::to_string_pretty(items).unwrap());
+    return;
+  }
+  let header = columns.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+  let rows = items.iter().map(|x| row(x).into_iter().map(|c| c.replace('\n', " ")).collect::<Vec<_>>()).collect::<Vec<_>>();
+  let widths = (0..columns.len()).map(|i| rows.iter().chain(Some(&header)).map(|r| r[i].width()).max().unwrap()).collect::<Vec<_>>();
+  for r in Some(&header).into_iter().chain(&rows) {
+    let cells = r.iter().zip(&widths).map(|(c, w)| format!("{}{}", c, " ".repeat(w - c.width()))).collect::<Vec<_>>();
+    println!("{}", cells.join("  ").trim_end());
+  }
+}
+
+fn time(t: &NaiveDateTime) -> String { t.format("%Y-%m-%d %H:%M").to_string() }
+
+fn option_time(t: &Option<NaiveDateTime>) -> String { t.as_ref().map_or(String::new(), time) }
+
+fn option<T: ToString>(x: &Option<T>) -> String { x.as_ref().map_or(String::new(), |x| x.to_string()) }
+
+fn semester(t: &LearnHelper, opt: &Opt) -> Result<SemesterId> {
+  match &opt.semester {
+    Some(x) => Ok(x.clone()),
+    None => Ok(t.current_semester()?.semester.into()),
+  }
+}
+
+// look up a course by its name, english name, course number or id
+fn course(t: &LearnHelper, opt: &Opt, key: &str) -> Result<Course> {
+  let cs = t.course_list(&semester(t, opt)?)?;
+  let (exact, others): (Vec<_>, Vec<_>) = cs.into_iter()
+    .partition(|c| c.id.as_str() == key || c.course_number == key || c.name == key || c.english_name.eq_ignore_ascii_case(key));
+  // try a prefix of the name, for example, "编译" for "编译原理"
+  let mut cs = if exact.is_empty() { others.into_iter().filter(|c| c.name.starts_with(key)).collect() } else { exact };
+  match cs.len() {
+    1 => Ok(cs.pop().unwrap()),
+    0 => Err(format!("no course matches \"{}\", see `thu-learn courses`", key).into()),
+    _ => Err(format!("\"{}\" matches more than one course: {}", key, cs.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ")).into()),
+  }
+}
+
+fn read_file(path: &Option<PathBuf>) -> Result<Option<(String, Vec<u8>)>> {
+  Ok(match path {
+    Some(path) => Some((path.file_name().ok_or("invalid file name")?.to_string_lossy().into_owned(), fs::read(path)?)),
+    None => None,
+  })
+}
+
+// the content as plain text in a table cell
+fn one_line(x: &impl Render, hosts: &Hosts) -> String { x.to_plain_text(hosts).split_whitespace().collect::<Vec<_>>().join(" ") }
+
+fn done(opt: &Opt, msg: &str) {
+  if opt.json { println!("{}", json!({ "result": "success" })); } else { println!("{}", msg); }
+}
+
+fn run(t: &LearnHelper, opt: &Opt) -> Result<()> {
+  match &opt.cmd {
+    Cmd::Semesters => print(opt, &t.semester_id_list()?, &["id"], |x| vec![x.to_string()]),
+    Cmd::Courses => print(opt, &t.course_list(&semester(t, opt)?)?, &["name", "teacher_name", "course_number", "course_index", "id"],
+      |c| vec![c.name.clone(), c.teacher_name.clone(), c.course_number.clone(), c.course_index.to_string(), c.id.to_string()]),
+    Cmd::Notifications { course: key } => print(opt, &t.notification_list(&course(t, opt, key)?.id)?,
+      &["title", "publisher", "publish_time", "read", "important", "attachment_name"],
+      |n| vec![n.title.clone(), n.publisher.clone(), time(&n.publish_time), n.read.to_string(), n.important.to_string(), option(&n.attachment_name)]),
+    Cmd::Files { course: key } => {
+      let id = course(t, opt, key)?.id;
+      let categories = t.file_categories(&id)?;
+      print(opt, &t.file_list(&id)?, &["title", "category", "size", "upload_time", "new", "important", "id"],
+        |f| vec![f.title.clone(), f.category(&categories).map_or(String::new(), |x| x.title.clone()), f.size.clone(), time(&f.upload_time), f.new.to_string(), f.important.to_string(), f.id.to_string()])
+    }
+    Cmd::Homework { course: key } => print(opt, &t.homework_list(&course(t, opt, key)?.id)?, &["title", "deadline", "status", "submit_time", "grade", "student_homework_id"],
+      |h| vec![h.title.clone(), time(&h.deadline), format!("{:?}", h.status), option_time(&h.submit_time), option(&h.grade), h.student_homework_id.to_string()]),
+    Cmd::Submit { student_homework, content, file } => {
+      let file = read_file(file)?;
+      t.submit_homework(student_homework, content.clone(), file.as_ref().map(|(name, data)| (name.as_str(), data.clone())))?;
+      done(opt, "submitted");
+    }
+    Cmd::Discussions { course: key } => print(opt, &t.discussion_list(&course(t, opt, key)?.id)?,
+      &["title", "publisher_name", "publish_time", "reply_count", "last_reply_time", "id"],
+      |d| vec![d.title.clone(), d.publisher_name.clone(), time(&d.publish_time), d.reply_count.to_string(), option_time(&d.last_reply_time), d.id.to_string()]),
+    Cmd::Questions { course: key } => print(opt, &t.question_list(&course(t, opt, key)?.id)?,
+      &["title", "publisher_name", "publish_time", "answered", "last_reply_time", "id"],
+      |q| vec![q.title.clone(), q.publisher_name.clone(), time(&q.publish_time), q.answered.to_string(), option_time(&q.last_reply_time), q.id.to_string()]),
+    Cmd::Question { course: key, question } => {
+      let q = t.question_detail(&course(t, opt, key)?.id, question)?;
+      if opt.json { println!("{}", serde_json::to_string_pretty(&q)?); } else {
+        println!("{}", q.to_plain_text(&t.hosts));
+        for a in &q.answers { println!("\n{} {}\n{}", a.author, time(&a.publish_time), a.to_plain_text(&t.hosts)); }
+      }
+    }
+    Cmd::Ask { course: key, title, content, file } => {
+      let file = read_file(file)?;
+      t.ask_question(&course(t, opt, key)?.id, title.clone(), content.clone(), file.as_ref().map(|(name, data)| (name.as_str(), data.clone())))?;
+      done(opt, "asked");
+    }
+    Cmd::Boards { course: key } => print(opt, &t.discussion_boards(&course(t, opt, key)?.id)?, &["title", "id"], |b| vec![b.title.clone(), b.id.to_string()]),
+    Cmd::Post { course: key, board, title, content, file } => {
+      let file = read_file(file)?;
+      t.create_discussion(&course(t, opt, key)?.id, board, title.clone(), content.clone(), file.as_ref().map(|(name, data)| (name.as_str(), data.clone())))?;
+      done(opt, "posted");
+    }
+    Cmd::EditDiscussion { course: key, discussion, title, content, file } => {
+      let file = read_file(file)?;
+      let course = course(t, opt, key)?;
+      let d = t.discussion_list(&course.id)?.into_iter().find(|d| d.id == *discussion).ok_or("no such discussion, see `thu-learn discussions`")?;
+      t.edit_discussion(&course.id, &d.id, &d.board_id, title.clone(), content.clone(), file.as_ref().map(|(name, data)| (name.as_str(), data.clone())))?;
+      done(opt, "edited");
+    }
+    Cmd::DeleteDiscussion { course: key, discussion } => {
+      t.delete_discussion(&course(t, opt, key)?.id, discussion)?;
+      done(opt, "deleted");
+    }
+    Cmd::Replies { course: key, discussion } => {
+      let course = course(t, opt, key)?;
+      let d = t.discussion_list(&course.id)?.into_iter().find(|d| d.id == *discussion).ok_or("no such discussion, see `thu-learn discussions`")?;
+      let rs = t.discussion_replies(&course.id, &d.id, &d.board_id)?;
+      if opt.json { print(opt, &rs, &[], |_| vec![]); } else {
+        // flatten the sub-replies into the table, with the authors indented
+        let mut rows = Vec::new();
+        for r in &rs {
+          rows.push(vec![r.author.clone(), time(&r.publish_time), one_line(r, &t.hosts), r.mine.to_string(), option(&r.id)]);
+          rows.extend(r.replies.iter().map(|s| vec![format!("  {}", s.author), time(&s.publish_time), one_line(s, &t.hosts), s.mine.to_string(), option(&s.id)]));
+        }
+        print(opt, &rows, &["author", "publish_time", "content", "mine", "id"], |r| r.clone());
+      }
+    }
+    Cmd::Reply { course: key, discussion, content, to, file } => {
+      let file = read_file(file)?;
+      let course = course(t, opt, key)?;
+      t.reply_discussion(&course.id, discussion, content.clone(), to.as_ref(), file.as_ref().map(|(name, data)| (name.as_str(), data.clone())))?;
+      done(opt, "replied");
+    }
+    Cmd::EditReply { course: key, discussion, reply, content, file } => {
+      let file = read_file(file)?;
+      t.edit_discussion_reply(&course(t, opt, key)?.id, discussion, reply, content.clone(), file.as_ref().map(|(name, data)| (name.as_str(), data.clone())))?;
+      done(opt, "edited");
+    }
+    Cmd::DeleteReply { course: key, discussion, reply } => {
+      let course = course(t, opt, key)?;
+      let d = t.discussion_list(&course.id)?.into_iter().find(|d| d.id == *discussion).ok_or("no such discussion, see `thu-learn discussions`")?;
+      match t.delete_discussion_reply(&course.id, &d.id, &d.board_id, reply, DELETE_DR_DEADLINE)? {
+        DeleteOutcome::Confirmed => done(opt, "deleted"),
+        DeleteOutcome::Assumed(e) => if opt.json { println!("{}", json!({ "result": "assumed", "error": e.to_string() })); } else {
+          println!("the deletion is sent, but it cannot be confirmed: {}", e);
+        },
+        DeleteOutcome::Failed => return Err("failed to delete the reply".into()),
+      }
+    }
+    Cmd::MarkRead { course: key } => {
+      let count = t.mark_all_read(&course(t, opt, key)?.id)?;
+      if opt.json { println!("{}", json!({ "count": count })); } else { println!("marked {} items as read", count); }
+    }
+    Cmd::Download { course: key, file, output } => {
+      let files = t.file_list(&course(t, opt, key)?.id)?;
+      let f = files.iter().find(|f| f.id.as_str() == file || f.title == *file).ok_or("no such file, see `thu-learn files`")?;
+      // the server's file name is unknown before downloading, so download to a temporary name first
+      let default = PathBuf::from(format!("{}.{}", f.title.replace(|c| "/\\:*?\"<>|".contains(c), "_"), f.file_type));
+      let path = output.clone().unwrap_or(default);
+      let mut part = path.clone().into_os_string();
+      part.push(".part");
+      let out = fs::OpenOptions::new().create(true).append(true).open(&part)?;
+      let offset = out.metadata()?.len();
+      let res = t.download_file(f, offset, out, |p| if !opt.json {
+        eprint!("\r{} / {} bytes", p.downloaded, p.total.map_or("?".to_owned(), |x| x.to_string()));
+      });
+      if !opt.json { eprintln!(); }
+      let res = res?;
+      let path = match (&res.file_name, output) { (Some(name), None) if !name.contains(|c| c == '/' || c == '\\') => PathBuf::from(name), _ => path };
+      fs::rename(&part, &path)?;
+      if opt.json { println!("{}", json!({ "path": path, "file_name": res.file_name, "size": res.size })); } else { println!("saved to {}", path.display()); }
+    }
+  }
+  Ok(())
+}
+
+fn main() {
+  let opt = Opt::from_args();
+  let res = (|| -> Result<()> {
+    let dir = config_dir();
+    let config_path = opt.config.clone().unwrap_or_else(|| dir.join("config.json"));
+    let config = match fs::read_to_string(&config_path) {
+      Ok(x) => serde_json::from_str(&x).map_err(|e| format!("invalid config file {}: {}", config_path.display(), e))?,
+      Err(e) if e.kind() == io::ErrorKind::NotFound && opt.config.is_none() => Config::default(),
+      Err(e) => return Err(format!("failed to read config file {}: {}", config_path.display(), e).into()),
+    };
+    let mut t = login(&dir, &config)?;
+    if t.credentials.is_none() { // ask for them only when they are really needed
+      let config = config.clone();
+      t.credentials = Some(Credentials::Provider(Arc::new(move || credentials(&config).ok())));
+    }
+    let res = run(&t, &opt);
+    save_session(&dir, &t)?;
+    res
+  })();
+  if let Err(e) = res {
+    eprintln!("thu-learn: {}", e);
+    std::process::exit(1);
+  }
+}
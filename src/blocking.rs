@@ -1,106 +1,548 @@
-use reqwest::{blocking::{Client, ClientBuilder, multipart::{Form, Part}}};
-use crate::{DELETE_DR_TIMEOUT, check_delete_dr_success};
-use crate::{parse::*, urls::*, types::*};
+use reqwest::{blocking::{Client, ClientBuilder, RequestBuilder, Response, multipart::{Form, Part}}, header, redirect::Policy};
+use serde::de::DeserializeOwned;
+use chrono::Utc;
+use std::{iter, thread, collections::VecDeque, io::{Read, Write}, path::Path, sync::Mutex, time::{Duration, Instant}};
+use crate::{Hosts, Credentials, Session, SessionState, DELETE_DR_TIMEOUT, MAX_REDIRECTS, check_delete_dr_success, check_confirm_error, delete_dr_outcome, has_reply, check_status, redirect_target};
+use crate::{download_start, download_chunk, check_download_size, Pager, page_items, OverviewPart, overview_parts, overview_merge, with_status, check_resubmission, pending_homework};
+use crate::{parse::*, urls::*, types::*, sync::{self, Manifest, SyncSummary, CourseContent}, watch::{self, Event, StateStore}};
 
 /// Same as `crate::LearnHelper`, except that it is a blocking api.
-pub struct LearnHelper(pub Client);
+pub struct LearnHelper {
+  /// Same as `crate::LearnHelper::client`, except that it is a blocking client.
+  pub client: Client,
+  /// Same as `crate::LearnHelper::hosts`.
+  pub hosts: Hosts,
+  /// Same as `crate::LearnHelper::credentials`.
+  pub credentials: Option<Credentials>,
+  /// Same as `crate::LearnHelper::session`.
+  pub session: Mutex<Session>,
+  /// Same as `crate::LearnHelper::relogin`, except that it is a blocking lock.
+  pub relogin: Mutex<()>,
+}
+
+fn client_builder() -> ClientBuilder { ClientBuilder::new().redirect(Policy::none()).user_agent(USER_AGENT) }
 
 impl LearnHelper {
   /// Same as `crate::LearnHelper::login`, except that it is a blocking api.
   pub fn login(username: &str, password: &str) -> Result<Self> {
-    let client = ClientBuilder::new().cookie_store(true).user_agent(USER_AGENT).build()?;
-    let params = [("i_user", username), ("i_pass", password), ("atOnce", "true")];
-    let res = client.post(LOGIN).form(&params).send()?.text()?;
-    let ticket_start = res.find("ticket=").ok_or("failed to login")? + 7;
-    let ticket_len = res[ticket_start..].find("\"").ok_or("failed to login")?;
-    client.post(&AUTH_ROAM(&res[ticket_start..ticket_start + ticket_len])).send()?;
-    Ok(Self(client))
+    Self::login_to(Hosts::default(), username, password)
+  }
+
+  /// Same as `crate::LearnHelper::login_to`, except that it is a blocking api.
+  pub fn login_to(hosts: Hosts, username: &str, password: &str) -> Result<Self> {
+    let helper = Self { client: client_builder().build()?, hosts, credentials: None, session: Default::default(), relogin: Default::default() };
+    helper.login_as(username, password)?;
+    Ok(helper)
+  }
+
+  /// Same as `crate::LearnHelper::login_with`, except that it is a blocking api.
+  pub fn login_with(hosts: Hosts, credentials: Credentials) -> Result<Self> {
+    let (username, password) = credentials.get().ok_or(Error::Auth)?;
+    let mut helper = Self::login_to(hosts, &username, &password)?;
+    helper.credentials = Some(credentials);
+    Ok(helper)
   }
 
   /// Same as `crate::LearnHelper::logout`, except that it is a blocking api.
   pub fn logout(self) -> Result<()> {
-    self.0.post(LOGOUT).send()?;
-    Ok(())
+    match self.send(self.post(LOGOUT)) {
+      Ok(_) | Err(Error::SessionExpired) => Ok(()),
+      Err(e) => Err(e),
+    }
+  }
+
+  /// Same as `crate::LearnHelper::save_session`.
+  pub fn save_session(&self) -> SessionState {
+    SessionState { hosts: self.hosts.clone(), session: self.session.lock().unwrap().clone() }
+  }
+
+  /// Same as `crate::LearnHelper::restore_session`, except that it returns a blocking api.
+  pub fn restore_session(state: SessionState) -> Result<Self> {
+    Ok(Self { client: client_builder().build()?, hosts: state.hosts, credentials: None, session: Mutex::new(state.session), relogin: Default::default() })
+  }
+
+  /// Same as `crate::LearnHelper::is_session_valid`, except that it is a blocking api.
+  pub fn is_session_valid(&self) -> Result<bool> {
+    match self.response_once(self.get(SEMESTER_LIST)) {
+      Ok(_) => Ok(true),
+      Err(Error::SessionExpired) => Ok(false),
+      Err(e) => Err(e),
+    }
   }
 
   /// Same as `crate::LearnHelper::semester_id_list`, except that it is a blocking api.
-  pub fn semester_id_list(&self) -> Result<Vec<Id>> {
-    let res = self.0.get(SEMESTER_LIST).send()?.json::<Vec<Option<String>>>()?;
+  pub fn semester_id_list(&self) -> Result<Vec<SemesterId>> {
+    let res = self.json::<Vec<Option<SemesterId>>>(SEMESTER_LIST)?;
     Ok(res.into_iter().filter_map(|x| x).collect())
   }
 
+  /// Same as `crate::LearnHelper::current_semester`, except that it is a blocking api.
+  pub fn current_semester(&self) -> Result<SemesterInfo> {
+    Ok(self.json::<JsonWrapper3<_>>(CURRENT_SEMESTER)?.result)
+  }
+
   /// Same as `crate::LearnHelper::course_list`, except that it is a blocking api.
-  pub fn course_list(&self, semester: IdRef) -> Result<Vec<Course>> {
-    let mut res = self.0.get(&COURSE_LIST(semester)).send()?.json::<JsonWrapper1<Course>>()?.resultList;
+  pub fn course_list(&self, semester: &SemesterId) -> Result<Vec<Course>> {
+    let mut res = self.json::<JsonWrapper1<Course>>(&COURSE_LIST(semester))?.resultList;
     for x in &mut res {
-      x.time_location = self.0.get(&COURSE_TIME_LOCATION(&x.id)).send()?.json()?;
+      x.time_location = self.json(&COURSE_TIME_LOCATION(&x.id))?;
     }
     Ok(res)
   }
 
   /// Same as `crate::LearnHelper::notification_list`, except that it is a blocking api.
-  pub fn notification_list(&self, course: IdRef) -> Result<Vec<Notification>> {
-    let mut res = self.0.get(&NOTIFICATION_LIST(course)).send()?.json::<JsonWrapper2<JsonWrapper20<Notification>>>()?.object.aaData;
-    for x in &mut res {
-      x.attachment_url = if x.attachment_name.is_some() {
-        const MSG: &str = "invalid notification attachment format";
-        let res = self.0.get(&NOTIFICATION_DETAIL(&x.id, course)).send()?.text()?;
-        let href_end = res.find("\" class=\"ml-10\"").ok_or(MSG)?;
-        let href_start = res[..href_end].rfind("a href=\"").ok_or(MSG)? + 8;
-        Some(PREFIX.to_owned() + &res[href_start..href_end])
-      } else { None };
-    }
+  pub fn notification_list(&self, course: &CourseId) -> Result<Vec<Notification>> {
+    let mut res = self.list::<Notification, NotificationPage>(|p| NOTIFICATION_LIST(course, p))?;
+    for x in &mut res { self.notification_attachment(x)?; }
     Ok(res)
   }
 
+  /// Same as `crate::LearnHelper::notifications_stream`, except that it is a blocking api, and it returns an iterator.
+  pub fn notifications_stream<'a>(&'a self, course: &'a CourseId) -> impl Iterator<Item = Result<Notification>> + 'a {
+    self.list_stream::<Notification, NotificationPage>(move |p| NOTIFICATION_LIST(course, p))
+      .map(move |x| { let mut x = x?; self.notification_attachment(&mut x)?; Ok(x) })
+  }
+
   /// Same as `crate::LearnHelper::file_list`, except that it is a blocking api.
-  pub fn file_list(&self, course: IdRef) -> Result<Vec<File>> {
-    Ok(self.0.get(&FILE_LIST(course)).send()?.json::<JsonWrapper2<Vec<File>>>()?.object)
+  pub fn file_list(&self, course: &CourseId) -> Result<Vec<File>> {
+    self.list::<_, JsonWrapper2<Vec<_>>>(|p| FILE_LIST(course, p))
+  }
+
+  /// Same as `crate::LearnHelper::files_stream`, except that it is a blocking api, and it returns an iterator.
+  pub fn files_stream<'a>(&'a self, course: &'a CourseId) -> impl Iterator<Item = Result<File>> + 'a {
+    self.list_stream::<_, JsonWrapper2<Vec<_>>>(move |p| FILE_LIST(course, p))
+  }
+
+  /// Same as `crate::LearnHelper::file_categories`, except that it is a blocking api.
+  pub fn file_categories(&self, course: &CourseId) -> Result<Vec<FileCategory>> {
+    let mut res = self.json::<JsonWrapper2<JsonWrapper22<FileCategory>>>(&FILE_CATEGORY_LIST(course))?.object.rows;
+    res.sort_by_key(|x| x.order);
+    Ok(res)
+  }
+
+  /// Same as `crate::LearnHelper::download_file`, except that it is a blocking api, and `out` is a blocking writer.
+  pub fn download_file(&self, file: &File, offset: u64, out: impl Write, progress: impl FnMut(DownloadProgress)) -> Result<Downloaded> {
+    self.download(&self.hosts.learn_url(&FILE_DOWNLOAD(&file.id)), offset, Some(file.raw_size as u64), out, progress)
+  }
+
+  /// Same as `crate::LearnHelper::download_attachment`, except that it is a blocking api, and `out` is a blocking writer.
+  pub fn download_attachment(&self, attachment: &Attachment, offset: u64, out: impl Write, progress: impl FnMut(DownloadProgress)) -> Result<Downloaded> {
+    self.download(&attachment.url, offset, None, out, progress)
+  }
+
+  /// Same as `crate::LearnHelper::mark_notification_read`, except that it is a blocking api.
+  pub fn mark_notification_read(&self, course: &CourseId, notification: &NotificationId) -> Result<()> {
+    check_success!(b, self.text(|| self.post(&NOTIFICATION_MARK_READ(course, notification))), "failed to mark notification as read")
+  }
+
+  /// Same as `crate::LearnHelper::mark_file_read`, except that it is a blocking api.
+  pub fn mark_file_read(&self, course: &CourseId, file: &FileId) -> Result<()> {
+    check_success!(b, self.text(|| self.post(&FILE_MARK_READ(course, file))), "failed to mark file as read")
+  }
+
+  /// Same as `crate::LearnHelper::mark_all_read`, except that it is a blocking api, and the requests are sent one by one.
+  pub fn mark_all_read(&self, course: &CourseId) -> Result<usize> {
+    let mut count = 0;
+    for x in self.list::<Notification, NotificationPage>(|p| NOTIFICATION_LIST(course, p))?.iter().filter(|x| !x.read) {
+      self.mark_notification_read(course, &x.id)?;
+      count += 1;
+    }
+    for x in self.file_list(course)?.iter().filter(|x| x.new) {
+      self.mark_file_read(course, &x.id)?;
+      count += 1;
+    }
+    Ok(count)
   }
 
   /// Same as `crate::LearnHelper::homework_list`, except that it is a blocking api.
-  pub fn homework_list(&self, course: IdRef) -> Result<Vec<Homework>> {
+  pub fn homework_list(&self, course: &CourseId) -> Result<Vec<Homework>> {
     let mut ret = Vec::new();
-    for f in &HOMEWORK_LIST_ALL {
-      let mut res = self.0.get(&f(course)).send()?.json::<JsonWrapper2<JsonWrapper20<Homework>>>()?.object.aaData;
-      for x in &mut res {
-        let res = self.0.get(&x.url()).send()?.text()?;
-        x.detail = parse_homework_detail(&res).ok_or("invalid homework detail format")?;
-      }
+    for (k, f) in HOMEWORK_LIST_ALL.iter().enumerate() {
+      let mut res = with_status(k, self.list::<_, JsonWrapper2<JsonWrapper20<Homework>>>(|p| f(course, p))?);
+      for x in &mut res { self.homework_detail(x)?; }
       ret.append(&mut res);
     }
     Ok(ret)
   }
 
+  /// Same as `crate::LearnHelper::homework_stream`, except that it is a blocking api, and it returns an iterator.
+  pub fn homework_stream<'a>(&'a self, course: &'a CourseId) -> impl Iterator<Item = Result<Homework>> + 'a {
+    HOMEWORK_LIST_ALL.iter().enumerate().flat_map(move |(k, f)| {
+      self.list_stream::<_, JsonWrapper2<JsonWrapper20<Homework>>>(move |p| f(course, p)).map(move |x| x.map(|mut x| { x.set_status(k); x }))
+    }).map(move |x| { let mut x = x?; self.homework_detail(&mut x)?; Ok(x) })
+  }
+
+  /// Same as `crate::LearnHelper::pending_homework`, except that it is a blocking api.
+  pub fn pending_homework(&self, semester: &SemesterId) -> Result<Vec<(Course, Homework)>> {
+    let options = OverviewOptions { notifications: false, files: false, discussions: false, ..Default::default() };
+    pending_homework(self.semester_overview(semester, &options)?)
+  }
+
   /// Same as `crate::LearnHelper::submit_homework`, except that it is a blocking api.
-  pub fn submit_homework(&self, student_homework: IdRef, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
-    let form = Form::new().text("zynr", content).text("xszyid", student_homework.to_owned()).text("isDeleted", "0");
-    let form = form_file!(form, file);
-    check_success!(b,  self.0.post(HOMEWORK_SUBMIT).multipart(form), "failed to submit homework")
+  pub fn submit_homework(&self, student_homework: &StudentHomeworkId, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    self.submit(student_homework, content, false, file)
+  }
+
+  /// Same as `crate::LearnHelper::resubmit_homework`, except that it is a blocking api.
+  pub fn resubmit_homework(&self, homework: &Homework, content: String, attachment: AttachmentChange<'_>) -> Result<Homework> {
+    let (deleted, file) = attachment.form();
+    self.submit(&homework.student_homework_id, content.clone(), deleted, file)?;
+    let mut res = self.homework_again(homework)?;
+    self.homework_detail(&mut res)?;
+    attachment.check(&homework.submit_attachment, &res.submit_attachment)?;
+    check_resubmission(homework, &res, &content)?;
+    Ok(res)
+  }
+
+  fn homework_again(&self, homework: &Homework) -> Result<Homework> {
+    for &k in &[1, 2, 0] {
+      let hs = self.list::<_, JsonWrapper2<JsonWrapper20<Homework>>>(|p| HOMEWORK_LIST_ALL[k](&homework.course_id, p))?;
+      if let Some(h) = with_status(k, hs).into_iter().find(|x| x.id == homework.id) { return Ok(h); }
+    }
+    Err("the homework is not found in the homework lists".into())
+  }
+
+  /// Same as `crate::LearnHelper::delete_homework_attachment`, except that it is a blocking api.
+  pub fn delete_homework_attachment(&self, homework: &Homework) -> Result<Homework> {
+    self.resubmit_homework(homework, homework.submit_content.clone().unwrap_or_default(), AttachmentChange::Remove)
+  }
+
+  fn submit(&self, student_homework: &StudentHomeworkId, content: String, deleted: bool, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    let form = || {
+      let form = Form::new().text("zynr", content.clone()).text("xszyid", student_homework.to_string()).text("isDeleted", if deleted { "1" } else { "0" });
+      form_file!(form, file.clone())
+    };
+    check_success!(b, self.text(|| self.post(HOMEWORK_SUBMIT).multipart(form())), "failed to submit homework")
   }
 
   /// Same as `crate::LearnHelper::discussion_list`, except that it is a blocking api.
-  pub fn discussion_list(&self, course: IdRef) -> Result<Vec<Discussion>> {
-    Ok(self.0.get(&DISCUSSION_LIST(course)).send()?.json::<JsonWrapper2<JsonWrapper21<_>>>()?.object.resultsList)
+  pub fn discussion_list(&self, course: &CourseId) -> Result<Vec<Discussion>> {
+    self.list::<_, JsonWrapper2<JsonWrapper21<_>>>(|p| DISCUSSION_LIST(course, p))
+  }
+
+  /// Same as `crate::LearnHelper::discussions_stream`, except that it is a blocking api, and it returns an iterator.
+  pub fn discussions_stream<'a>(&'a self, course: &'a CourseId) -> impl Iterator<Item = Result<Discussion>> + 'a {
+    self.list_stream::<_, JsonWrapper2<JsonWrapper21<_>>>(move |p| DISCUSSION_LIST(course, p))
+  }
+
+  /// Same as `crate::LearnHelper::discussion_boards`, except that it is a blocking api.
+  pub fn discussion_boards(&self, course: &CourseId) -> Result<Vec<DiscussionBoard>> {
+    Ok(self.json::<JsonWrapper2<JsonWrapper22<DiscussionBoard>>>(&DISCUSSION_BOARD_LIST(course))?.object.rows)
   }
 
   /// Same as `crate::LearnHelper::discussion_replies`, except that it is a blocking api.
-  pub fn discussion_replies(&self, course: IdRef, discussion: IdRef, discussion_board: IdRef) -> Result<Vec<DiscussionReply>> {
-    let res = self.0.get(&DISCUSSION_REPLIES(course, discussion, discussion_board)).send()?.text()?;
-    parse_discussion_replies(&res).ok_or("invalid discussion replies format".into())
+  pub fn discussion_replies(&self, course: &CourseId, discussion: &DiscussionId, discussion_board: &BoardId) -> Result<Vec<DiscussionReply>> {
+    let url = self.hosts.learn_url(&DISCUSSION_REPLIES(course, discussion, discussion_board));
+    let res = self.text(|| self.client.get(&url))?;
+    parse_discussion_replies(&res).map_err(|e| e.into_error("discussion replies", url))
+  }
+
+  /// Same as `crate::LearnHelper::question_list`, except that it is a blocking api.
+  pub fn question_list(&self, course: &CourseId) -> Result<Vec<Question>> {
+    self.list::<_, JsonWrapper2<JsonWrapper21<_>>>(|p| QUESTION_LIST(course, p))
+  }
+
+  /// Same as `crate::LearnHelper::questions_stream`, except that it is a blocking api, and it returns an iterator.
+  pub fn questions_stream<'a>(&'a self, course: &'a CourseId) -> impl Iterator<Item = Result<Question>> + 'a {
+    self.list_stream::<_, JsonWrapper2<JsonWrapper21<_>>>(move |p| QUESTION_LIST(course, p))
+  }
+
+  /// Same as `crate::LearnHelper::question_detail`, except that it is a blocking api.
+  pub fn question_detail(&self, course: &CourseId, question: &QuestionId) -> Result<QuestionDetail> {
+    let url = self.hosts.learn_url(&QUESTION_DETAIL(course, question));
+    let res = self.text(|| self.client.get(&url))?;
+    parse_question_detail(&res).map_err(|e| e.into_error("question detail", url))
+  }
+
+  /// Same as `crate::LearnHelper::ask_question`, except that it is a blocking api.
+  pub fn ask_question(&self, course: &CourseId, title: String, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    let form = || {
+      let form = Form::new().text("wlkcid", course.to_string()).text("bt", title.clone()).text("nr", content.clone());
+      form_file!(form, file.clone())
+    };
+    check_success!(b, self.text(|| self.post(ASK_QUESTION).multipart(form())), "failed to ask question")
   }
 
   /// Same as `crate::LearnHelper::reply_discussion`, except that it is a blocking api.
-  pub fn reply_discussion(&self, course: IdRef, discussion: IdRef, content: String, respondent_reply: Option<IdRef<'_>>, file: Option<(&str, Vec<u8>)>) -> Result<()> {
-    let form = Form::new().text("wlkcid", course.to_owned()).text("tltid", discussion.to_owned()).text("nr", content.to_owned());
-    let form = form_file!(form, file);
-    let form = if let Some(x) = respondent_reply { form.text("fhhid", x.to_owned()).text("_fhhid", x.to_owned()) } else { form };
-    check_success!(b, self.0.post(REPLY_DISCUSSION).multipart(form), "failed to reply discussion")
+  pub fn reply_discussion(&self, course: &CourseId, discussion: &DiscussionId, content: String, respondent_reply: Option<&ReplyId>, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    let form = || {
+      let form = Form::new().text("wlkcid", course.to_string()).text("tltid", discussion.to_string()).text("nr", content.clone());
+      let form = form_file!(form, file.clone());
+      if let Some(x) = respondent_reply { form.text("fhhid", x.to_string()).text("_fhhid", x.to_string()) } else { form }
+    };
+    check_success!(b, self.text(|| self.post(REPLY_DISCUSSION).multipart(form())), "failed to reply discussion")
+  }
+
+  /// Same as `crate::LearnHelper::edit_discussion_reply`, except that it is a blocking api.
+  pub fn edit_discussion_reply(&self, course: &CourseId, discussion: &DiscussionId, reply: &ReplyId, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    let form = || {
+      let form = Form::new().text("wlkcid", course.to_string()).text("tltid", discussion.to_string()).text("id", reply.to_string()).text("nr", content.clone());
+      form_file!(form, file.clone())
+    };
+    check_success!(b, self.text(|| self.post(REPLY_DISCUSSION).multipart(form())), "failed to edit discussion reply")
   }
 
   /// Same as `crate::LearnHelper::delete_discussion_reply`, except that it is a blocking api.
-  pub fn delete_discussion_reply(&self, course: IdRef, reply: IdRef) -> Result<()> {
-    check_delete_dr_success(
-      self.0.post(&DELETE_DISCUSSION_REPLY(course, reply)).timeout(DELETE_DR_TIMEOUT).send().and_then(|r| r.text()))
+  pub fn delete_discussion_reply(&self, course: &CourseId, discussion: &DiscussionId, discussion_board: &BoardId, reply: &ReplyId, deadline: Duration) -> Result<DeleteOutcome> {
+    let res = self.text(|| self.post(&DELETE_DISCUSSION_REPLY(course, reply)).timeout(DELETE_DR_TIMEOUT));
+    if let Some(x) = check_delete_dr_success(res)? { return Ok(x); }
+    let (start, mut fetched, mut error) = (Instant::now(), false, None);
+    loop {
+      match self.discussion_replies(course, discussion, discussion_board) {
+        Ok(rs) if !has_reply(&rs, reply) => return Ok(DeleteOutcome::Confirmed),
+        Ok(_) => fetched = true,
+        Err(e) => error = Some(check_confirm_error(e)?),
+      }
+      if start.elapsed() >= deadline { return Ok(delete_dr_outcome(fetched, error)); }
+      thread::sleep(DELETE_DR_TIMEOUT);
+    }
+  }
+
+  /// Same as `crate::LearnHelper::create_discussion`, except that it is a blocking api.
+  pub fn create_discussion(&self, course: &CourseId, board: &BoardId, title: String, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    self.save_discussion(course, None, board, title, content, file)
+  }
+
+  /// Same as `crate::LearnHelper::edit_discussion`, except that it is a blocking api.
+  pub fn edit_discussion(&self, course: &CourseId, discussion: &DiscussionId, board: &BoardId, title: String, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    self.save_discussion(course, Some(discussion), board, title, content, file)
+  }
+
+  /// Same as `crate::LearnHelper::delete_discussion`, except that it is a blocking api.
+  pub fn delete_discussion(&self, course: &CourseId, discussion: &DiscussionId) -> Result<()> {
+    check_success!(b, self.text(|| self.post(&DELETE_DISCUSSION(course, discussion))), "failed to delete discussion")
+  }
+
+  fn save_discussion(&self, course: &CourseId, discussion: Option<&DiscussionId>, board: &BoardId, title: String, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    let form = || {
+      let form = Form::new().text("wlkcid", course.to_string()).text("bqid", board.to_string()).text("bt", title.clone()).text("nr", content.clone());
+      let form = form_file!(form, file.clone());
+      if let Some(x) = discussion { form.text("id", x.to_string()) } else { form }
+    };
+    check_success!(b, self.text(|| self.post(SAVE_DISCUSSION).multipart(form())), "failed to save discussion")
+  }
+
+  /// Same as `crate::LearnHelper::sync_semester`, except that it is a blocking api.
+  pub fn sync_semester(&self, root: impl AsRef<Path>, semester: &SemesterId) -> Result<SyncSummary> {
+    let root = root.as_ref();
+    let mut contents = Vec::new();
+    for course in self.course_list(semester)? {
+      let file_categories = self.file_categories(&course.id)?;
+      let (files, homework, notifications) = (self.file_list(&course.id)?, self.homework_list(&course.id)?, self.notification_list(&course.id)?);
+      contents.push(CourseContent { course, file_categories, files, homework, notifications });
+    }
+    let plan = sync::plan(root, &self.hosts.learn, &Manifest::load(root)?, &contents);
+    for path in &plan.removes { sync::remove(root, path)?; }
+    for (path, content) in &plan.writes { sync::write(root, path, content)?; }
+    for d in &plan.downloads {
+      let (file, offset) = sync::open_part(root, &d.path, d.restart)?;
+      let res = self.download(&d.url, offset, d.size, file, |_| {});
+      sync::finish_part(root, &d.path, &res)?;
+      res?;
+    }
+    plan.manifest.save(root)?;
+    Ok(plan.summary)
+  }
+
+  fn download(&self, url: &str, offset: u64, expected: Option<u64>, mut out: impl Write, mut progress: impl FnMut(DownloadProgress)) -> Result<Downloaded> {
+    let req = || if offset == 0 { self.client.get(url) } else { self.client.get(url).header(header::RANGE, format!("bytes={}-", offset)) };
+    let mut res = match self.response(req) {
+      Err(Error::Server { status: 416, .. }) if offset != 0 => return check_download_size(url, expected, Downloaded { file_name: None, size: offset }),
+      res => res?,
+    };
+    let mut start = download_start(res.status(), res.headers(), res.content_length(), offset);
+    let (mut downloaded, mut buf) = (offset, vec![0; 64 * 1024]);
+    loop {
+      let len = res.read(&mut buf)?;
+      if len == 0 { break; }
+      let chunk = download_chunk(&mut start, &buf[..len]);
+      out.write_all(chunk)?;
+      downloaded += chunk.len() as u64;
+      progress(DownloadProgress { downloaded, total: start.total });
+    }
+    out.flush()?;
+    check_download_size(url, expected.or(start.total), Downloaded { file_name: start.file_name, size: downloaded })
+  }
+
+  /// Same as `crate::LearnHelper::semester_overview`, except that it is a blocking api, and it sends the requests one by one, ignoring `options.concurrency`.
+  pub fn semester_overview(&self, semester: &SemesterId, options: &OverviewOptions) -> Result<SemesterSnapshot> {
+    let courses = self.json::<JsonWrapper1<Course>>(&COURSE_LIST(semester))?.resultList;
+    let mut snapshots = courses.into_iter().map(CourseSnapshot::new).collect::<Vec<_>>();
+    for (i, category, k) in overview_parts(snapshots.len(), options) {
+      let res = self.overview_part(&snapshots[i].course.id, category, k);
+      overview_merge(&mut snapshots[i], category, res);
+    }
+    for s in &mut snapshots {
+      let res = s.notifications.iter_mut().flatten().try_for_each(|x| self.notification_attachment(x));
+      if let Err(e) = res { s.fail(Category::Notifications, e); }
+      let res = s.homework.iter_mut().flatten().try_for_each(|x| self.homework_detail(x));
+      if let Err(e) = res { s.fail(Category::Homework, e); }
+    }
+    Ok(SemesterSnapshot { semester: semester.clone(), courses: snapshots })
+  }
+
+  /// Same as `crate::LearnHelper::watch`, except that it is a blocking api, and it returns an iterator, which sleeps between the polls.
+  pub fn watch<'a>(&'a self, semester: &'a SemesterId, interval: Duration, options: &'a OverviewOptions, mut store: impl StateStore + 'a) -> impl Iterator<Item = Event> + 'a {
+    let (mut events, mut first) = (VecDeque::new(), true);
+    iter::from_fn(move || {
+      while events.is_empty() {
+        if !first { thread::sleep(interval); }
+        first = false;
+        events = watch::poll(&mut store, self.semester_overview(semester, options)).into();
+      }
+      events.pop_front()
+    })
+  }
+
+  fn overview_part(&self, course: &CourseId, category: Category, k: usize) -> Result<OverviewPart> {
+    Ok(match category {
+      Category::TimeLocation => OverviewPart::TimeLocation(self.json(&COURSE_TIME_LOCATION(course))?),
+      Category::Notifications => OverviewPart::Notifications(self.list::<Notification, NotificationPage>(|p| NOTIFICATION_LIST(course, p))?),
+      Category::Files => OverviewPart::Files(self.file_list(course)?),
+      Category::Homework => OverviewPart::Homework(with_status(k, self.list::<_, JsonWrapper2<JsonWrapper20<_>>>(|p| HOMEWORK_LIST_ALL[k](course, p))?)),
+      Category::Discussions => OverviewPart::Discussions(self.discussion_list(course)?),
+    })
+  }
+
+  fn notification_attachment(&self, x: &mut Notification) -> Result<()> {
+    x.attachment = if let Some(name) = x.attachment_name.clone() {
+      let url = self.hosts.learn_url(&NOTIFICATION_DETAIL(&x.id, &x.course_id));
+      let res = self.text(|| self.client.get(&url))?;
+      let url = parse_notification_attachment(&res, &self.hosts.learn).map_err(|e| e.into_error("notification detail", url))?;
+      Some(Attachment { name, url })
+    } else { None };
+    Ok(())
+  }
+
+  fn homework_detail(&self, x: &mut Homework) -> Result<()> {
+    let url = self.hosts.learn_url(&HOMEWORK_DETAIL(&x.course_id, &x.id, &x.student_homework_id));
+    let res = self.text(|| self.client.get(&url))?;
+    x.detail = parse_homework_detail(&res, &self.hosts.learn).map_err(|e| e.into_error("homework detail", url))?;
+    Ok(())
+  }
+
+  fn pages<'a, T: 'a, W: Page<T> + 'a>(&'a self, url: impl Fn(usize) -> String + 'a) -> impl Iterator<Item = Result<Vec<T>>> + 'a {
+    let mut pager = Pager::default();
+    iter::from_fn(move || {
+      let path = pager.next(&url)?;
+      let text = self.text(|| self.get(&path));
+      Some(pager.feed::<T, W>(self.hosts.learn_url(&path), text))
+    })
+  }
+
+  fn list_stream<'a, T: 'a, W: Page<T> + 'a>(&'a self, url: impl Fn(usize) -> String + 'a) -> impl Iterator<Item = Result<T>> + 'a {
+    self.pages::<T, W>(url).flat_map(page_items)
+  }
+
+  fn list<T, W: Page<T>>(&self, url: impl Fn(usize) -> String) -> Result<Vec<T>> {
+    let mut res = Vec::new();
+    for x in self.pages::<T, W>(url) { res.append(&mut x?); }
+    Ok(res)
+  }
+
+  fn get(&self, path: &str) -> RequestBuilder { self.client.get(&self.hosts.learn_url(path)) }
+
+  fn text(&self, req: impl Fn() -> RequestBuilder) -> Result<String> {
+    Ok(self.response(req)?.text()?)
+  }
+
+  fn response(&self, req: impl Fn() -> RequestBuilder) -> Result<Response> {
+    let generation = self.session.lock().unwrap().generation;
+    match self.response_once(req()) {
+      Err(Error::SessionExpired) if self.credentials.is_some() => {
+        self.relogin(generation)?;
+        self.response_once(req())
+      }
+      res => res,
+    }
+  }
+
+  fn relogin(&self, generation: u64) -> Result<()> {
+    let _guard = self.relogin.lock().unwrap();
+    if self.session.lock().unwrap().generation != generation { return Ok(()); }
+    let (username, password) = self.credentials.as_ref().unwrap().get().ok_or(Error::SessionExpired)?;
+    self.login_as(&username, &password)
+  }
+
+  fn response_once(&self, req: RequestBuilder) -> Result<Response> {
+    let res = self.send(req)?;
+    check_status(res.status(), res.url())?;
+    Ok(res)
+  }
+
+  fn send(&self, req: RequestBuilder) -> Result<Response> {
+    let cookie = self.session.lock().unwrap().cookie_header();
+    let mut res = req.header(header::COOKIE, cookie).send()?;
+    for _ in 0..MAX_REDIRECTS {
+      if self.hosts.is_learn(res.url()) { self.session.lock().unwrap().store(res.cookies()); }
+      let url = match redirect_target(&self.hosts, res.status(), res.url(), res.headers())? { Some(url) => url, None => return Ok(res) };
+      let cookie = if self.hosts.is_learn(&url) { self.session.lock().unwrap().cookie_header() } else { String::new() };
+      res = self.client.get(url).header(header::COOKIE, cookie).send()?;
+    }
+    Err("too many redirections".into())
+  }
+
+  fn json<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+    parse_json(self.hosts.learn_url(path), &self.text(|| self.get(path))?)
+  }
+
+  fn login_as(&self, username: &str, password: &str) -> Result<()> {
+    let params = [("i_user", username), ("i_pass", password), ("atOnce", "true")];
+    let res = self.client.post(&self.hosts.id_url(LOGIN)).form(&params).send()?.text()?;
+    let ticket_start = res.find("ticket=").ok_or(Error::Auth)? + 7;
+    let ticket_len = res[ticket_start..].find("\"").ok_or(Error::Auth)?;
+    let generation = self.session.lock().unwrap().generation;
+    *self.session.lock().unwrap() = Session { generation, ..Session::default() };
+    if let Err(e) = self.send(self.post(&AUTH_ROAM(&res[ticket_start..ticket_start + ticket_len]))) {
+      return Err(if let Error::SessionExpired = e { Error::Auth } else { e });
+    }
+    let mut session = self.session.lock().unwrap();
+    session.login_time = Some(Utc::now());
+    session.generation += 1;
+    Ok(())
+  }
+
+  fn post(&self, path: &str) -> RequestBuilder { self.client.post(&self.hosts.learn_url(path)) }
+}
+
+/// The blocking versions of the methods of `Course` that take a `crate::LearnHelper`, they are suffixed with `_blocking`.
+impl Course {
+  /// Same as `Course::notification_list`, except that it is a blocking api.
+  pub fn notification_list_blocking(&self, t: &LearnHelper) -> Result<Vec<Notification>> { t.notification_list(&self.id) }
+
+  /// Same as `Course::file_list`, except that it is a blocking api.
+  pub fn file_list_blocking(&self, t: &LearnHelper) -> Result<Vec<File>> { t.file_list(&self.id) }
+
+  /// Same as `Course::file_categories`, except that it is a blocking api.
+  pub fn file_categories_blocking(&self, t: &LearnHelper) -> Result<Vec<FileCategory>> { t.file_categories(&self.id) }
+
+  /// Same as `Course::homework_list`, except that it is a blocking api.
+  pub fn homework_list_blocking(&self, t: &LearnHelper) -> Result<Vec<Homework>> { t.homework_list(&self.id) }
+
+  /// Same as `Course::discussion_list`, except that it is a blocking api.
+  pub fn discussion_list_blocking(&self, t: &LearnHelper) -> Result<Vec<Discussion>> { t.discussion_list(&self.id) }
+
+  /// Same as `Course::discussion_boards`, except that it is a blocking api.
+  pub fn discussion_boards_blocking(&self, t: &LearnHelper) -> Result<Vec<DiscussionBoard>> { t.discussion_boards(&self.id) }
+
+  /// Same as `Course::question_list`, except that it is a blocking api.
+  pub fn question_list_blocking(&self, t: &LearnHelper) -> Result<Vec<Question>> { t.question_list(&self.id) }
+
+  /// Same as `Course::discussion_replies`, except that it is a blocking api.
+  pub fn discussion_replies_blocking(&self, t: &LearnHelper, discussion: &Discussion) -> Result<Vec<DiscussionReply>> {
+    t.discussion_replies(&self.id, &discussion.id, &discussion.board_id)
+  }
+}
+
+/// The blocking versions of the methods of `Homework` that take a `crate::LearnHelper`, they are suffixed with `_blocking`.
+impl Homework {
+  /// Same as `Homework::submit`, except that it is a blocking api.
+  pub fn submit_blocking(&self, t: &LearnHelper, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    t.submit_homework(&self.student_homework_id, content, file)
+  }
+
+  /// Same as `Homework::resubmit`, except that it is a blocking api.
+  pub fn resubmit_blocking(&self, t: &LearnHelper, content: String, attachment: AttachmentChange<'_>) -> Result<Homework> {
+    t.resubmit_homework(self, content, attachment)
   }
-}
\ No newline at end of file
+}
@@ -0,0 +1,2 @@
+/// Exports homework deadlines and the course timetable as an iCalendar (.ics) file.
+pub mod ical;
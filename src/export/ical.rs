@@ -0,0 +1,162 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, DateTime, Utc};
+use std::{fmt, collections::HashSet};
+use crate::{Hosts, render::Render, sync::hash, types::*};
+
+/// The start and end time of each "大节" (`TimeSlot::first_period` and `TimeSlot::last_period`) in Tsinghua, in Beijing time.
+pub const PERIODS: [((u32, u32), (u32, u32)); 6] = [((8, 0), (9, 35)), ((9, 50), (12, 15)), ((13, 30), (15, 5)), ((15, 20), (16, 55)), ((17, 5), (18, 40)), ((19, 20), (21, 45))];
+
+/// The number of teaching weeks in a semester, which is what `Weeks::All`, `Weeks::Odd` and `Weeks::Even` refer to.
+pub const TEACHING_WEEKS: u8 = 16;
+
+// all the times from web-learning are in Beijing time, which is UTC+8 without daylight saving time
+const UTC_OFFSET: i64 = 8;
+
+// appears in the UIDs, so that they don't clash with the events from other sources
+const UID_DOMAIN: &str = "learn.tsinghua.edu.cn";
+
+/// An iCalendar file being built, `to_string` gives the content of the file.
+///
+/// Every event has a UID derived from `Course::id` and the time slot, or `Homework::id`,
+/// so importing a newer export of the same semester updates the events instead of duplicating them.
+pub struct Calendar {
+  hosts: Hosts,
+  stamp: DateTime<Utc>,
+  components: String,
+}
+
+impl Default for Calendar {
+  fn default() -> Self { Self::new(&Hosts::default()) }
+}
+
+impl Calendar {
+  /// Create an empty calendar, the urls of the events are on `hosts`, which is usually `LearnHelper::hosts`.
+  pub fn new(hosts: &Hosts) -> Self { Self { hosts: hosts.clone(), stamp: Utc::now(), components: String::new() } }
+
+  /// Add a VTODO for each homework assignment of a course, its due time is `Homework::deadline`,
+  /// and it is completed when the homework is submitted. The grade (if any) goes to the description, with the comment in plain text.
+  pub fn homework(&mut self, course: &Course, homework: &[Homework]) -> &mut Self {
+    for h in homework {
+      let mut c = Component::new("VTODO", format!("homework-{}-{}", h.course_id, h.id), self.stamp);
+      c.text("SUMMARY", &format!("[{}] {}", course.name, h.title));
+      c.time("DTSTART", h.assign_time);
+      c.time("DUE", h.deadline);
+      let mut description = Vec::new();
+      if let Some(grade) = h.grade { description.push(format!("成绩: {}", grade)); }
+      if let Some(grader) = &h.grader_name { description.push(format!("批阅人: {}", grader)); }
+      if let Some(content) = &h.grade_content { description.push(format!("评语: {}", content.to_plain_text(&self.hosts))); }
+      if !description.is_empty() { c.text("DESCRIPTION", &description.join("\n")); }
+      c.line("URL", &h.url(&self.hosts));
+      match h.submit_time {
+        Some(t) => { c.line("STATUS", "COMPLETED"); c.time("COMPLETED", t); }
+        None => c.line("STATUS", "NEEDS-ACTION"),
+      }
+      self.components += &c.end();
+    }
+    self
+  }
+
+  /// Add weekly recurring VEVENTs for the timetable of a course, `semester_start` is any day in the first week of the semester.
+  ///
+  /// The elements of `Course::time_location` in an unknown format (`TimeLocation::Raw`) are skipped, so are the ranges of `Weeks::Explicit` that are empty or include week 0.
+  pub fn timetable(&mut self, course: &Course, semester_start: NaiveDate) -> &mut Self {
+    let monday = semester_start - Duration::days(semester_start.weekday().num_days_from_monday() as i64);
+    let mut uids = HashSet::new();
+    for slot in course.time_slots() {
+      let slot = match slot { TimeLocation::Slot(x) => x, TimeLocation::Raw(_) => continue };
+      // `wrapping_sub` makes period 0 out of range as well
+      let (start, end) = match (PERIODS.get((slot.first_period as usize).wrapping_sub(1)), PERIODS.get((slot.last_period as usize).wrapping_sub(1))) {
+        (Some(&((h1, m1), _)), Some(&(_, (h2, m2)))) => (NaiveTime::from_hms_opt(h1, m1, 0).unwrap(), NaiveTime::from_hms_opt(h2, m2, 0).unwrap()),
+        _ => continue,
+      };
+      // (first week, interval, count)
+      let rules = match &slot.weeks {
+        Weeks::All => vec![(1, 1, TEACHING_WEEKS)],
+        Weeks::Odd => vec![(1, 2, (TEACHING_WEEKS + 1) / 2)],
+        Weeks::Even => vec![(2, 2, TEACHING_WEEKS / 2)],
+        Weeks::Explicit(ranges) => ranges.iter().filter(|x| 1 <= x.0 && x.0 <= x.1).map(|&(first, last)| (first, 1, last - first + 1)).collect(),
+      };
+      for (first, interval, count) in rules {
+        // the uid only depends on the content of the slot, so that it doesn't change when the slots are reordered, and a repeated slot is added once
+        let classroom = hash(slot.classroom.as_deref().unwrap_or(""));
+        let uid = format!("course-{}-{}-{}-{}-{}-{}-{}-{:x}", course.id, slot.weekday.number_from_monday(), slot.first_period, slot.last_period, first, interval, count, classroom);
+        if !uids.insert(uid.clone()) { continue; }
+        let day = monday + Duration::weeks(first as i64 - 1) + Duration::days(slot.weekday.num_days_from_monday() as i64);
+        let mut c = Component::new("VEVENT", uid, self.stamp);
+        c.text("SUMMARY", &course.name);
+        if let Some(classroom) = &slot.classroom { c.text("LOCATION", classroom); }
+        c.text("DESCRIPTION", &course.teacher_name);
+        c.time("DTSTART", day.and_time(start));
+        c.time("DTEND", day.and_time(end));
+        c.line("RRULE", &format!("FREQ=WEEKLY;INTERVAL={};COUNT={}", interval, count));
+        c.line("URL", &course.url(&self.hosts));
+        self.components += &c.end();
+      }
+    }
+    self
+  }
+
+  /// Add the homework assignments and the timetable of all the courses in a snapshot from `LearnHelper::semester_overview`.
+  pub fn snapshot(&mut self, snapshot: &SemesterSnapshot, semester_start: NaiveDate) -> &mut Self {
+    for c in &snapshot.courses {
+      self.timetable(&c.course, semester_start);
+      if let Some(homework) = &c.homework { self.homework(&c.course, homework); }
+    }
+    self
+  }
+}
+
+impl fmt::Display for Calendar {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//thu-learn-helper//EN\r\nCALSCALE:GREGORIAN\r\n{}END:VCALENDAR\r\n", self.components)
+  }
+}
+
+// a VEVENT or a VTODO being built
+struct Component { name: &'static str, content: String }
+
+impl Component {
+  fn new(name: &'static str, uid: String, stamp: DateTime<Utc>) -> Self {
+    let mut c = Self { name, content: format!("BEGIN:{}\r\n", name) };
+    c.line("UID", &format!("{}@{}", uid, UID_DOMAIN));
+    c.line("DTSTAMP", &stamp.format("%Y%m%dT%H%M%SZ").to_string());
+    c
+  }
+
+  // a content line, folded to at most 75 octets per line as RFC 5545 requires
+  fn line(&mut self, name: &str, value: &str) {
+    let mut len = 0;
+    for ch in name.chars().chain(std::iter::once(':')).chain(value.chars()) {
+      if len + ch.len_utf8() > 75 {
+        self.content += "\r\n ";
+        len = 1;
+      }
+      self.content.push(ch);
+      len += ch.len_utf8();
+    }
+    self.content += "\r\n";
+  }
+
+  fn text(&mut self, name: &str, value: &str) {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+      match ch {
+        '\\' | ';' | ',' => { escaped.push('\\'); escaped.push(ch); }
+        '\n' => escaped += "\\n",
+        '\r' => {}
+        _ => escaped.push(ch),
+      }
+    }
+    self.line(name, &escaped);
+  }
+
+  // `t` is in Beijing time, it is written in UTC
+  fn time(&mut self, name: &str, t: NaiveDateTime) {
+    self.line(name, &(t - Duration::hours(UTC_OFFSET)).format("%Y%m%dT%H%M%SZ").to_string());
+  }
+
+  fn end(mut self) -> String {
+    let name = self.name;
+    self.line("END", name);
+    self.content
+  }
+}
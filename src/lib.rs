@@ -8,11 +8,24 @@ mod parse;
 mod urls;
 /// Defines data structures of the information fetched from web-learning.
 pub mod types;
+/// Exports the fetched information to the formats that other applications understand.
+pub mod export;
+/// Mirrors all the courses of a semester to a local directory, only downloading the new or changed items.
+pub mod sync;
+/// Watches a semester for changes, see `LearnHelper::watch`.
+pub mod watch;
+/// Converts the html contents, like `Notification::content`, to plain text or markdown.
+pub mod render;
+/// A fake web-learning server serving canned data, need `features = ["test-server"]` to enable.
+#[cfg(feature = "test-server")]
+pub mod test_server;
 
-use reqwest::{Client, ClientBuilder, multipart::{Form, Part}};
-use futures::future::{try_join3, try_join_all};
-use std::time::Duration;
-use crate::{parse::*, urls::*, types::*};
+use reqwest::{Client, ClientBuilder, RequestBuilder, Response, StatusCode, Url, cookie::Cookie, header, multipart::{Form, Part}, redirect::Policy};
+use futures::{lock::Mutex as AsyncMutex, future::{Either, ready, try_join, try_join3, try_join4, try_join_all}, io::{AllowStdIo, AsyncWrite, AsyncWriteExt}, stream::{self, Stream, StreamExt, TryStreamExt}};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use chrono::{DateTime, Utc};
+use std::{fmt, collections::{BTreeMap, VecDeque}, path::Path, sync::{Arc, Mutex}, time::{Duration, Instant, SystemTime}};
+use crate::{parse::*, urls::*, types::*, sync::{Manifest, SyncSummary, CourseContent}, watch::{Event, StateStore}};
 
 #[macro_use]
 mod macros {
@@ -26,10 +39,10 @@ mod macros {
     };
   }
 
-  // `a` for `async`, `b` for `blocking`
+  // `a` for `async`, `b` for `blocking`, `$text` is the response text, which is a future in async api
   macro_rules! check_success {
-    (a, $req: expr, $msg: expr) => { if $req.send().await?.text().await?.contains("success") { Ok(()) } else { Err($msg.into()) } };
-    (b, $req: expr, $msg: expr) => { if $req.send()?.text()?.contains("success") { Ok(()) } else { Err($msg.into()) } };
+    (a, $text: expr, $msg: expr) => { if $text.await?.contains("success") { Ok(()) } else { Err($msg.into()) } };
+    (b, $text: expr, $msg: expr) => { if $text?.contains("success") { Ok(()) } else { Err($msg.into()) } };
   }
 }
 
@@ -41,23 +54,309 @@ pub mod blocking; // it has to be placed after `macros` to use the macros inside
 ///
 /// When deleting a discussion reply from web learning, it takes a long time before a successful reply is sent back,
 /// but it normally fails fast when the deletion is illegal.
-/// So here we use a 1 second timeout limit, and if it is reached, we consider the deletion as successful.
+/// So here we use a 1 second timeout limit, and if it is reached, the deletion is confirmed by fetching the replies again,
+/// once every `DELETE_DR_TIMEOUT` until the deadline passes.
 pub const DELETE_DR_TIMEOUT: Duration = Duration::from_secs(1);
 
-pub(crate) fn check_delete_dr_success(r: reqwest::Result<String>) -> Result<()> {
-  match r.map(|x| x.contains("success")) {
-    Ok(true) => Ok(()),
-    Err(e) if e.is_timeout() => Ok(()),
-    _ => Err("failed to delete discussion reply".into())
+/// A reasonable `deadline` of `LearnHelper::delete_discussion_reply`.
+pub const DELETE_DR_DEADLINE: Duration = Duration::from_secs(10);
+
+/// The number of items that the list apis (`LearnHelper::notification_list`, `LearnHelper::file_list`, etc.) request in each page.
+///
+/// The lists are fetched page by page until a page has fewer items than this, so a busy course doesn't lose any item.
+pub const PAGE_SIZE: usize = 200;
+
+/// The maximum number of requests that `LearnHelper::mark_all_read` sends at the same time.
+pub const MARK_READ_CONCURRENCY: usize = 8;
+
+// fetches a paginated list page by page, `page` is the last page requested
+#[derive(Default)]
+pub(crate) struct Pager {
+  page: usize,
+  last: Option<String>,
+  done: bool,
+}
+
+impl Pager {
+  // the path of the next page, `None` if the list is exhausted or an error happened
+  pub(crate) fn next(&mut self, url: impl Fn(usize) -> String) -> Option<String> {
+    if self.done { None } else { self.page += 1; Some(url(self.page)) }
+  }
+
+  // `text` is the response of the path that `next` returned, and `url` is its full url
+  pub(crate) fn feed<T, W: Page<T>>(&mut self, url: String, text: Result<String>) -> Result<Vec<T>> {
+    self.done = true;
+    let text = text?;
+    // a server that ignores "currentPage" would return the first page again and again
+    if self.last.as_ref() == Some(&text) { return Ok(Vec::new()); }
+    let res = parse_json::<W>(url, &text)?.items();
+    self.done = res.len() < PAGE_SIZE;
+    self.last = Some(text);
+    Ok(res)
+  }
+}
+
+pub(crate) fn page_items<T>(page: Result<Vec<T>>) -> Vec<Result<T>> {
+  match page { Ok(x) => x.into_iter().map(Ok).collect(), Err(e) => vec![Err(e)] }
+}
+
+// `None` if the deletion timed out, then it has to be confirmed by `discussion_replies`
+pub(crate) fn check_delete_dr_success(r: Result<String>) -> Result<Option<DeleteOutcome>> {
+  match r {
+    Ok(x) => Ok(Some(if x.contains("success") { DeleteOutcome::Confirmed } else { DeleteOutcome::Failed })),
+    Err(Error::Network(e)) if e.is_timeout() => Ok(None),
+    Err(e) => Err(e),
+  }
+}
+
+// the error of fetching the replies to confirm a deletion, login failures are returned, while the others make the deletion `Assumed`
+pub(crate) fn check_confirm_error(e: Error) -> Result<Error> {
+  match e { Error::Auth | Error::SessionExpired => Err(e), e => Ok(e) }
+}
+
+// the outcome when the deadline of confirming a deletion passes, `error` is the error of the last failed fetch
+pub(crate) fn delete_dr_outcome(fetched: bool, error: Option<Error>) -> DeleteOutcome {
+  match error {
+    Some(e) if !fetched => DeleteOutcome::Assumed(e),
+    _ => DeleteOutcome::Failed,
+  }
+}
+
+// whether `reply` is one of `replies` or their sub-replies
+pub(crate) fn has_reply(replies: &[DiscussionReply], reply: &ReplyId) -> bool {
+  replies.iter().any(|r| r.id.as_ref() == Some(reply) || r.replies.iter().any(|x| x.id.as_ref() == Some(reply)))
+}
+
+// `reqwest` follows redirects by itself by default, but the cookies set in the middle of the redirections are invisible to us
+pub(crate) const MAX_REDIRECTS: usize = 10;
+
+pub(crate) fn redirect_target(hosts: &Hosts, status: StatusCode, url: &Url, headers: &header::HeaderMap) -> Result<Option<Url>> {
+  if !status.is_redirection() { return Ok(None); }
+  let location = headers.get(header::LOCATION).and_then(|x| x.to_str().ok()).ok_or("invalid redirection")?;
+  let url = url.join(location).map_err(|_| "invalid redirection")?;
+  if hosts.is_login_page(&url) { Err(Error::SessionExpired) } else { Ok(Some(url)) }
+}
+
+pub(crate) fn check_status(status: StatusCode, url: &Url) -> Result<()> {
+  if status.is_success() { Ok(()) } else { Err(Error::Server { status: status.as_u16(), url: url.to_string() }) }
+}
+
+// how a download goes on after the response headers arrive, `offset` is where the download resumes from
+pub(crate) struct DownloadStart {
+  // the number of leading bytes of the body to drop, it is non-zero if the server ignores "Range" and sends the whole file
+  skip: u64,
+  total: Option<u64>,
+  file_name: Option<String>,
+}
+
+pub(crate) fn download_start(status: StatusCode, headers: &header::HeaderMap, content_length: Option<u64>, offset: u64) -> DownloadStart {
+  let file_name = headers.get(header::CONTENT_DISPOSITION).and_then(|x| parse_content_disposition(x.as_bytes()));
+  if status == StatusCode::PARTIAL_CONTENT {
+    // "Content-Range: bytes 100-199/200"
+    let total = headers.get(header::CONTENT_RANGE).and_then(|x| x.to_str().ok()?.rsplit('/').next()?.parse().ok());
+    DownloadStart { skip: 0, total: total.or(content_length.map(|x| x + offset)), file_name }
+  } else { DownloadStart { skip: offset, total: content_length, file_name } }
+}
+
+// the part of `chunk` that should be written, and update `start.skip` accordingly
+pub(crate) fn download_chunk<'a>(start: &mut DownloadStart, chunk: &'a [u8]) -> &'a [u8] {
+  let skip = start.skip.min(chunk.len() as u64);
+  start.skip -= skip;
+  &chunk[skip as usize..]
+}
+
+pub(crate) fn check_download_size(url: &str, expected: Option<u64>, res: Downloaded) -> Result<Downloaded> {
+  match expected {
+    Some(expected) if expected != res.size => Err(Error::SizeMismatch { url: url.to_owned(), expected, actual: res.size }),
+    _ => Ok(res),
+  }
+}
+
+// the result of a single list request in `semester_overview`
+pub(crate) enum OverviewPart {
+  TimeLocation(Vec<String>),
+  Notifications(Vec<Notification>),
+  Files(Vec<File>),
+  Homework(Vec<Homework>),
+  Discussions(Vec<Discussion>),
+}
+
+// `list` is the index in `HOMEWORK_LIST_ALL` that `res` comes from
+pub(crate) fn with_status(list: usize, mut res: Vec<Homework>) -> Vec<Homework> {
+  for x in &mut res { x.set_status(list); }
+  res
+}
+
+// `after` is `before` read back after submitting `content`
+pub(crate) fn check_resubmission(before: &Homework, after: &Homework, content: &str) -> Result<()> {
+  if after.submit_content.as_deref().unwrap_or("").trim() != content.trim() { return Err("the content of the submission is not changed as requested".into()); }
+  match after.submit_time {
+    Some(t) if before.submit_time.map_or(true, |x| x <= t) => Ok(()),
+    _ => Err("the submission is not recorded by web-learning".into()),
+  }
+}
+
+pub(crate) fn pending_homework(snapshot: SemesterSnapshot) -> Result<Vec<(Course, Homework)>> {
+  let now = beijing_now();
+  let mut res = Vec::new();
+  for mut c in snapshot.courses {
+    if let Some(i) = c.errors.iter().position(|x| x.0 == Category::Homework) { return Err(c.errors.swap_remove(i).1); }
+    for h in c.homework.take().unwrap_or_default() {
+      if h.status_at(now) == HomeworkStatus::NotSubmitted { res.push((c.course.clone(), h)); }
+    }
+  }
+  res.sort_by_key(|x| x.1.deadline);
+  Ok(res)
+}
+
+// the list requests of `semester_overview`, `(course index, category, index in HOMEWORK_LIST_ALL)`
+pub(crate) fn overview_parts(courses: usize, options: &OverviewOptions) -> Vec<(usize, Category, usize)> {
+  let mut res = Vec::new();
+  for i in 0..courses {
+    res.push((i, Category::TimeLocation, 0));
+    if options.notifications { res.push((i, Category::Notifications, 0)); }
+    if options.files { res.push((i, Category::Files, 0)); }
+    if options.homework { for k in 0..HOMEWORK_LIST_ALL.len() { res.push((i, Category::Homework, k)); } }
+    if options.discussions { res.push((i, Category::Discussions, 0)); }
+  }
+  res
+}
+
+pub(crate) fn overview_merge(s: &mut CourseSnapshot, category: Category, res: Result<OverviewPart>) {
+  match res {
+    Ok(OverviewPart::TimeLocation(x)) => s.course.time_location = x,
+    Ok(OverviewPart::Notifications(x)) => s.notifications = Some(x),
+    Ok(OverviewPart::Files(x)) => s.files = Some(x),
+    // the 3 homework lists are merged, and a failure of any of them fails the whole category
+    Ok(OverviewPart::Homework(mut x)) => if s.error(Category::Homework).is_none() { s.homework.get_or_insert_with(Vec::new).append(&mut x); },
+    Ok(OverviewPart::Discussions(x)) => s.discussions = Some(x),
+    Err(e) => s.fail(category, e),
+  }
+}
+
+/// The hosts that a `LearnHelper` communicates with, neither of them has a trailing slash.
+///
+/// The default value points to the real web-learning. Other values are mainly useful for testing,
+/// for example, pointing both of them to a `test_server::TestServer`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hosts {
+  /// The host that performs login, "https://id.tsinghua.edu.cn" by default.
+  pub id: String,
+  /// The host of web-learning, "https://learn.tsinghua.edu.cn" by default.
+  pub learn: String,
+}
+
+impl Default for Hosts {
+  fn default() -> Self { Self { id: ID_PREFIX.to_owned(), learn: PREFIX.to_owned() } }
+}
+
+impl Hosts {
+  pub(crate) fn id_url(&self, path: &str) -> String { self.id.clone() + path }
+
+  pub(crate) fn learn_url(&self, path: &str) -> String { self.learn.clone() + path }
+
+  pub(crate) fn is_learn(&self, url: &Url) -> bool { url.as_str().starts_with(&self.learn) }
+
+  // web-learning redirects to its login page when the session is expired or not logged in at all
+  pub(crate) fn is_login_page(&self, url: &Url) -> bool {
+    (self.is_learn(url) && url.path() == LOGIN_PAGE) || (url.as_str().starts_with(&self.id) && url.path().starts_with(ID_LOGIN_PAGES))
+  }
+}
+
+/// The cookies of `Hosts::learn` that identify a login session (mainly "JSESSIONID"), and the time the session was established.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+  /// `name => value` of the cookies.
+  pub cookies: BTreeMap<String, String>,
+  /// When exists, it is the time of the last successful login.
+  pub login_time: Option<DateTime<Utc>>,
+  /// The number of successful logins again of the `LearnHelper` that owns it, it is not saved.
+  /// A request that fails with an older generation retries with the current session, instead of login again.
+  #[serde(skip)] pub generation: u64,
+}
+
+impl Session {
+  pub(crate) fn cookie_header(&self) -> String {
+    self.cookies.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("; ")
+  }
+
+  pub(crate) fn store<'a>(&mut self, cookies: impl Iterator<Item = Cookie<'a>>) {
+    for c in cookies {
+      let expired = c.value().is_empty() || c.max_age() == Some(Duration::from_secs(0)) || c.expires().map_or(false, |t| t <= SystemTime::now());
+      if expired { self.cookies.remove(c.name()); } else { self.cookies.insert(c.name().to_owned(), c.value().to_owned()); }
+    }
+  }
+}
+
+/// A serializable snapshot of a login session, returned by `LearnHelper::save_session` and consumed by `LearnHelper::restore_session`.
+///
+/// It contains the session cookies, so treat it as carefully as a password.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionState {
+  /// The hosts that the session belongs to.
+  pub hosts: Hosts,
+  /// The session itself.
+  #[serde(flatten)]
+  pub session: Session,
+}
+
+/// Where a `LearnHelper` gets the username and password from when it needs to login again.
+#[derive(Clone)]
+pub enum Credentials {
+  /// The username and password are stored in memory.
+  Stored {
+    /// The username.
+    username: String,
+    /// The password.
+    password: String,
+  },
+  /// The callback is called every time re-login is needed, and returns `(username, password)`.
+  /// Returning `None` means the credentials are unavailable, and the request fails with `Error::SessionExpired`.
+  Provider(Arc<dyn Fn() -> Option<(String, String)> + Send + Sync>),
+}
+
+impl Credentials {
+  pub(crate) fn get(&self) -> Option<(String, String)> {
+    match self {
+      Credentials::Stored { username, password } => Some((username.clone(), password.clone())),
+      Credentials::Provider(f) => f(),
+    }
+  }
+}
+
+// don't print the password
+impl fmt::Debug for Credentials {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Credentials::Stored { username, .. } => write!(f, "Stored {{ username: {:?}, .. }}", username),
+      Credentials::Provider(_) => write!(f, "Provider(..)"),
+    }
   }
 }
 
 /// The core struct type, representing a login session to web-learning.
 ///
-/// It is only a simple wrapper of `reqwest::Client`, and it is also a public field,
-/// because I don't care about user modifying it, or create a `LearnHelper` instance through `LearnHelper(...)`.
+/// It is only a simple wrapper of `reqwest::Client` and the `Hosts` it talks to, and they are also public fields,
+/// because I don't care about user modifying them, or create a `LearnHelper` instance through `LearnHelper { ... }`.
 /// After all they will have to pay a price (getting `Err` result) if their action is not proper.
-pub struct LearnHelper(pub Client);
+///
+/// When the session expires, every request fails with `Error::SessionExpired`, unless `credentials` exists,
+/// in which case it logins again with `credentials` and retries the request once.
+/// The concurrent requests that find the session expired at the same time share one login.
+pub struct LearnHelper {
+  /// The http client. It should not follow redirections or keep cookies by itself, `LearnHelper` does these things.
+  pub client: Client,
+  /// The hosts that this session communicates with.
+  pub hosts: Hosts,
+  /// When exists, it is used to login again when the session expires.
+  pub credentials: Option<Credentials>,
+  /// The cookies of the login session.
+  pub session: Mutex<Session>,
+  /// Held while logging in again, so that only one of the concurrent requests does it.
+  pub relogin: AsyncMutex<()>,
+}
+
+pub(crate) fn client_builder() -> ClientBuilder { ClientBuilder::new().redirect(Policy::none()).user_agent(USER_AGENT) }
 
 // compiler requires type annotation in async closure, so extract them here
 const OK: Result<()> = Ok(());
@@ -65,110 +364,308 @@ const OK: Result<()> = Ok(());
 impl LearnHelper {
   /// Do login with the given `username` and `password`.
   ///
-  /// If you want to create a `LearnHelper` instance with other configuration, you can simply use `LearnHelper(...)` to construct one.
+  /// If you want to create a `LearnHelper` instance with other configuration, you can simply use `LearnHelper { ... }` to construct one.
   ///
-  /// If the `username` or `password` is wrong, it will generally result in an `Err`.
+  /// If the `username` or `password` is wrong, it will generally result in an `Err(Error::Auth)`.
   pub async fn login(username: &str, password: &str) -> Result<Self> {
-    let client = ClientBuilder::new().cookie_store(true).user_agent(USER_AGENT).build()?;
-    let params = [("i_user", username), ("i_pass", password), ("atOnce", "true")];
-    let res = client.post(LOGIN).form(&params).send().await?.text().await?;
-    let ticket_start = res.find("ticket=").ok_or("failed to login")? + 7; // 7 == "ticket=".len()
-    let ticket_len = res[ticket_start..].find("\"").ok_or("failed to login")?;
-    client.post(&AUTH_ROAM(&res[ticket_start..ticket_start + ticket_len])).send().await?;
-    Ok(Self(client))
+    Self::login_to(Hosts::default(), username, password).await
+  }
+
+  /// Same as `login`, except that it communicates with the given `hosts` instead of the real web-learning.
+  pub async fn login_to(hosts: Hosts, username: &str, password: &str) -> Result<Self> {
+    let helper = Self { client: client_builder().build()?, hosts, credentials: None, session: Default::default(), relogin: Default::default() };
+    helper.login_as(username, password).await?;
+    Ok(helper)
+  }
+
+  /// Same as `login_to`, except that the username and password come from `credentials`,
+  /// and they are kept to login again automatically when the session expires.
+  pub async fn login_with(hosts: Hosts, credentials: Credentials) -> Result<Self> {
+    let (username, password) = credentials.get().ok_or(Error::Auth)?;
+    let mut helper = Self::login_to(hosts, &username, &password).await?;
+    helper.credentials = Some(credentials);
+    Ok(helper)
   }
 
   /// Logout from web-learning, and end the login session, consuming `self`.
   ///
   /// You may logout if you wish, and it is not necessary.
   pub async fn logout(self) -> Result<()> {
-    self.0.post(LOGOUT).send().await?;
-    Ok(())
+    match self.send(self.post(LOGOUT)).await {
+      Ok(_) | Err(Error::SessionExpired) => Ok(()), // it may redirect to the login page
+      Err(e) => Err(e),
+    }
+  }
+
+  /// Return a snapshot of the current login session, which can be saved somewhere (it is serde-serializable),
+  /// and later be used by `restore_session` to continue the session without login again.
+  pub fn save_session(&self) -> SessionState {
+    SessionState { hosts: self.hosts.clone(), session: self.session.lock().unwrap().clone() }
+  }
+
+  /// Continue a session saved by `save_session`, without sending any request.
+  ///
+  /// The session may have expired in the meantime, you can check it with `is_session_valid`,
+  /// or set `credentials` of the returned value to login again automatically.
+  pub fn restore_session(state: SessionState) -> Result<Self> {
+    Ok(Self { client: client_builder().build()?, hosts: state.hosts, credentials: None, session: Mutex::new(state.session), relogin: Default::default() })
+  }
+
+  /// Check whether the session is still valid with a cheap request. It never tries to login again.
+  pub async fn is_session_valid(&self) -> Result<bool> {
+    match self.response_once(self.get(SEMESTER_LIST)).await {
+      Ok(_) => Ok(true),
+      Err(Error::SessionExpired) => Ok(false),
+      Err(e) => Err(e),
+    }
   }
 
-  /// Return a list of semester ids of this student. These ids will later be referred to as `semester: IdRef`.
+  /// Return a list of semester ids of this student. These ids will later be referred to as `semester: &SemesterId`.
   ///
   /// A semester id has the form of "year1-year2-[1/2/3]", where `1` means fall, `2` means spring, `3` means summer.
-  /// This is define by constants `SEMESTER_FALL`, `SEMESTER_SPRING`, `SEMESTER_SUMMER`.
-  pub async fn semester_id_list(&self) -> Result<Vec<Id>> {
-    let res = self.0.get(SEMESTER_LIST).send().await?.json::<Vec<Option<String>>>().await?;
+  /// This is define by constants `SEMESTER_FALL`, `SEMESTER_SPRING`, `SEMESTER_SUMMER`, and `Semester` parses it.
+  pub async fn semester_id_list(&self) -> Result<Vec<SemesterId>> {
+    let res = self.json::<Vec<Option<SemesterId>>>(SEMESTER_LIST).await?;
     Ok(res.into_iter().filter_map(|x| x).collect()) // there is `null` in response
   }
 
+  /// Return the current semester and its start and end dates, which is the semester that web-learning shows by default.
+  ///
+  /// Prefer it to the first element of `semester_id_list`, which can be the next semester when it is about to begin.
+  pub async fn current_semester(&self) -> Result<SemesterInfo> {
+    Ok(self.json::<JsonWrapper3<_>>(CURRENT_SEMESTER).await?.result)
+  }
+
   /// Return a list of courses of a given semester. Parameter `semester` refers to the return value of `semester_id_list`.
-  pub async fn course_list(&self, semester: IdRef<'_>) -> Result<Vec<Course>> {
-    let mut res = self.0.get(&COURSE_LIST(semester)).send().await?.json::<JsonWrapper1<Course>>().await?.resultList;
+  pub async fn course_list(&self, semester: &SemesterId) -> Result<Vec<Course>> {
+    let mut res = self.json::<JsonWrapper1<Course>>(&COURSE_LIST(semester)).await?.resultList;
     try_join_all(res.iter_mut().map(async move |x| {
-      x.time_location = self.0.get(&COURSE_TIME_LOCATION(&x.id)).send().await?.json().await?;
+      x.time_location = self.json(&COURSE_TIME_LOCATION(&x.id)).await?;
       OK
     })).await?;
     Ok(res)
   }
 
   /// Return a list of discussions of a given course. Parameter `course` refers to `Course::id`.
-  pub async fn notification_list(&self, course: IdRef<'_>) -> Result<Vec<Notification>> {
-    let mut res = self.0.get(&NOTIFICATION_LIST(course)).send().await?.json::<JsonWrapper2<JsonWrapper20<Notification>>>().await?.object.aaData;
-    try_join_all(res.iter_mut().map(async move |x| {
-      x.attachment_url = if x.attachment_name.is_some() {
-        const MSG: &str = "invalid notification attachment format";
-        let res = self.0.get(&NOTIFICATION_DETAIL(&x.id, course)).send().await?.text().await?;
-        let href_end = res.find("\" class=\"ml-10\"").ok_or(MSG)?;
-        let href_start = res[..href_end].rfind("a href=\"").ok_or(MSG)? + 8;
-        Some(PREFIX.to_owned() + &res[href_start..href_end])
-      } else { None };
-      OK
-    })).await?;
+  ///
+  /// Like the other list apis, it fetches all the pages of the list, see `PAGE_SIZE`.
+  pub async fn notification_list(&self, course: &CourseId) -> Result<Vec<Notification>> {
+    let mut res = self.list::<Notification, NotificationPage>(|p| NOTIFICATION_LIST(course, p)).await?;
+    try_join_all(res.iter_mut().map(|x| self.notification_attachment(x))).await?;
     Ok(res)
   }
 
+  /// Same as `notification_list`, except that it returns a stream, which fetches the pages and the attachments only when they are polled,
+  /// so that the consumer can process the notifications incrementally, or stop early by dropping the stream.
+  ///
+  /// It doesn't fetch further pages after a page fails, so an `Err` item caused by the list is the last item.
+  pub fn notifications_stream<'a>(&'a self, course: &'a CourseId) -> impl Stream<Item = Result<Notification>> + 'a {
+    self.list_stream::<Notification, NotificationPage>(move |p| NOTIFICATION_LIST(course, p))
+      .and_then(move |mut x| async move { self.notification_attachment(&mut x).await?; Ok::<_, Error>(x) })
+  }
+
   /// Return a list of files of a given course. Parameter `course` refers to `Course::id`.
-  pub async fn file_list(&self, course: IdRef<'_>) -> Result<Vec<File>> {
-    Ok(self.0.get(&FILE_LIST(course)).send().await?.json::<JsonWrapper2<Vec<File>>>().await?.object)
+  pub async fn file_list(&self, course: &CourseId) -> Result<Vec<File>> {
+    self.list::<_, JsonWrapper2<Vec<_>>>(|p| FILE_LIST(course, p)).await
+  }
+
+  /// Same as `file_list`, except that it returns a stream, see `notifications_stream`.
+  pub fn files_stream<'a>(&'a self, course: &'a CourseId) -> impl Stream<Item = Result<File>> + 'a {
+    self.list_stream::<_, JsonWrapper2<Vec<_>>>(move |p| FILE_LIST(course, p))
+  }
+
+  /// Return the file categories of a given course, sorted by `FileCategory::order`. Parameter `course` refers to `Course::id`.
+  ///
+  /// `File::category_id` refers to one of them, and `File::category` looks it up.
+  pub async fn file_categories(&self, course: &CourseId) -> Result<Vec<FileCategory>> {
+    let mut res = self.json::<JsonWrapper2<JsonWrapper22<FileCategory>>>(&FILE_CATEGORY_LIST(course)).await?.object.rows;
+    res.sort_by_key(|x| x.order);
+    Ok(res)
+  }
+
+  /// Download a file to `out`, return the file name given by the server and the size of the whole file.
+  /// - Parameter `file` refers to an element in the return value of `file_list`.
+  /// - Parameter `offset` is the number of bytes that `out` already has, the download resumes from there with a "Range" request.
+  /// It should be 0 for a new download. To resume a partial download, open the destination in append mode and pass its length.
+  /// - Parameter `out`: the content after `offset` is written to it.
+  /// - Parameter `progress` is called every time a chunk is written.
+  ///
+  /// When the size of the whole file is different from `File::raw_size`, it returns `Error::SizeMismatch`.
+  pub async fn download_file(&self, file: &File, offset: u64, out: impl AsyncWrite + Unpin, progress: impl FnMut(DownloadProgress)) -> Result<Downloaded> {
+    self.download(&self.hosts.learn_url(&FILE_DOWNLOAD(&file.id)), offset, Some(file.raw_size as u64), out, progress).await
+  }
+
+  /// Download an attachment of a notification or a homework, the parameters and the return value have the same meaning as in `download_file`.
+  ///
+  /// When the size of the whole file is different from what the server claims, it returns `Error::SizeMismatch`.
+  pub async fn download_attachment(&self, attachment: &Attachment, offset: u64, out: impl AsyncWrite + Unpin, progress: impl FnMut(DownloadProgress)) -> Result<Downloaded> {
+    self.download(&attachment.url, offset, None, out, progress).await
+  }
+
+  /// Mark a notification as read, as if it is opened in the browser.
+  /// - Parameter `course` refers to `Course::id`.
+  /// - Parameter `notification` refers to `Notification::id`.
+  pub async fn mark_notification_read(&self, course: &CourseId, notification: &NotificationId) -> Result<()> {
+    check_success!(a, self.text(|| self.post(&NOTIFICATION_MARK_READ(course, notification))), "failed to mark notification as read")
+  }
+
+  /// Mark a file as read, as if it is opened in the browser.
+  /// - Parameter `course` refers to `Course::id`.
+  /// - Parameter `file` refers to `File::id`.
+  pub async fn mark_file_read(&self, course: &CourseId, file: &FileId) -> Result<()> {
+    check_success!(a, self.text(|| self.post(&FILE_MARK_READ(course, file))), "failed to mark file as read")
+  }
+
+  /// Mark all the unread notifications (`!Notification::read`) and files (`File::new`) of a course as read, return the number of them.
+  /// Parameter `course` refers to `Course::id`.
+  ///
+  /// The requests are sent concurrently, at most `MARK_READ_CONCURRENCY` at the same time.
+  pub async fn mark_all_read(&self, course: &CourseId) -> Result<usize> {
+    let (ns, fs) = try_join(self.list::<Notification, NotificationPage>(|p| NOTIFICATION_LIST(course, p)), self.file_list(course)).await?;
+    let ns = ns.into_iter().filter(|x| !x.read).collect::<Vec<_>>();
+    let fs = fs.into_iter().filter(|x| x.new).collect::<Vec<_>>();
+    let n = ns.iter().map(|x| Either::Left(self.mark_notification_read(course, &x.id)));
+    let f = fs.iter().map(|x| Either::Right(self.mark_file_read(course, &x.id)));
+    let res = stream::iter(n.chain(f)).buffer_unordered(MARK_READ_CONCURRENCY).collect::<Vec<_>>().await;
+    res.into_iter().collect::<Result<()>>()?;
+    Ok(ns.len() + fs.len())
   }
 
   /// Return a list of homework assignments of a given course. Parameter `course` refers to `Course::id`.
-  pub async fn homework_list(&self, course: IdRef<'_>) -> Result<Vec<Homework>> {
-    let f = async move |f: fn(&str) -> String| {
-      let mut res = self.0.get(&f(course)).send().await?.json::<JsonWrapper2<JsonWrapper20<Homework>>>().await?.object.aaData;
-      try_join_all(res.iter_mut().map(async move |x| {
-        let res = self.0.get(&x.url()).send().await?.text().await?;
-        x.detail = parse_homework_detail(&res).ok_or("invalid homework detail format")?;
-        OK
-      })).await?;
+  pub async fn homework_list(&self, course: &CourseId) -> Result<Vec<Homework>> {
+    let f = async move |k: usize| {
+      let mut res = with_status(k, self.list::<_, JsonWrapper2<JsonWrapper20<Homework>>>(|p| HOMEWORK_LIST_ALL[k](course, p)).await?);
+      try_join_all(res.iter_mut().map(|x| self.homework_detail(x))).await?;
       Ok::<_, Error>(res)
     };
-    let (mut res, mut h1, mut h2) = try_join3(f(HOMEWORK_LIST_ALL[0]), f(HOMEWORK_LIST_ALL[1]), f(HOMEWORK_LIST_ALL[2])).await?;
+    let (mut res, mut h1, mut h2) = try_join3(f(0), f(1), f(2)).await?;
     res.reserve(h1.len() + h2.len());
     res.append(&mut h1);
     res.append(&mut h2);
     Ok(res)
   }
 
+  /// Same as `homework_list`, except that it returns a stream, see `notifications_stream`.
+  pub fn homework_stream<'a>(&'a self, course: &'a CourseId) -> impl Stream<Item = Result<Homework>> + 'a {
+    stream::iter(HOMEWORK_LIST_ALL.iter().enumerate()).flat_map(move |(k, f)| {
+      self.list_stream::<_, JsonWrapper2<JsonWrapper20<Homework>>>(move |p| f(course, p)).map_ok(move |mut x| { x.set_status(k); x })
+    }).and_then(move |mut x| async move { self.homework_detail(&mut x).await?; Ok::<_, Error>(x) })
+  }
+
+  /// Return the homework assignments of all the courses in a semester that are not submitted and not overdue (`HomeworkStatus::NotSubmitted`),
+  /// together with their courses, sorted by deadline. Parameter `semester` refers to the return value of `semester_id_list`.
+  ///
+  /// It fetches with `semester_overview`, but unlike it, failing to fetch the homework of any course returns `Err`, so that no homework is missed silently.
+  pub async fn pending_homework(&self, semester: &SemesterId) -> Result<Vec<(Course, Homework)>> {
+    let options = OverviewOptions { notifications: false, files: false, discussions: false, ..Default::default() };
+    pending_homework(self.semester_overview(semester, &options).await?)
+  }
+
   /// Submitting homework to a given homework assignment.
   /// - Parameter `student_homework` refers to `Homework::student_homework_id`.
   /// - Parameter `content` is the content of your submission.
   /// - Parameter `file` is `(file name, file content)` when it exists. File name is only used in
   ///   web-learning, this function won't perform file reading.
-  pub async fn submit_homework(&self, student_homework: IdRef<'_>, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
-    // the performance loss caused by defining parameters as IdRef instead of impl Into<Cow<'static, str>> is negligible
-    // however giving them type IdRef makes the api much clearer
-    let form = Form::new().text("zynr", content).text("xszyid", student_homework.to_owned()).text("isDeleted", "0");
-    let form = form_file!(form, file);
-    check_success!(a, self.0.post(HOMEWORK_SUBMIT).multipart(form), "failed to submit homework")
+  pub async fn submit_homework(&self, student_homework: &StudentHomeworkId, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    // every kind of id has its own type, so passing `Homework::id` here fails to compile
+    self.submit(student_homework, content, false, file).await
+  }
+
+  /// Submit a homework again, replacing the content of the previous submission with `content`,
+  /// and doing what `attachment` says to the attachment of the previous submission. It also works for a homework that is not submitted yet.
+  /// - Parameter `homework` refers to an element in the return value of `homework_list`.
+  ///
+  /// After submitting, it fetches the homework from the homework lists and its detail page again, and returns `Err` if the submission read back
+  /// doesn't have `content`, has no submit time or an older one, or its attachment is not what `attachment` asks for.
+  /// Otherwise it returns the homework read back, so every field of it comes from web-learning.
+  pub async fn resubmit_homework(&self, homework: &Homework, content: String, attachment: AttachmentChange<'_>) -> Result<Homework> {
+    let (deleted, file) = attachment.form();
+    self.submit(&homework.student_homework_id, content.clone(), deleted, file).await?;
+    let mut res = self.homework_again(homework).await?;
+    self.homework_detail(&mut res).await?;
+    attachment.check(&homework.submit_attachment, &res.submit_attachment)?;
+    check_resubmission(homework, &res, &content)?;
+    Ok(res)
+  }
+
+  // find `homework` in the homework lists of its course, the lists that a submitted homework is likely in go first
+  async fn homework_again(&self, homework: &Homework) -> Result<Homework> {
+    for &k in &[1, 2, 0] {
+      let hs = self.list::<_, JsonWrapper2<JsonWrapper20<Homework>>>(|p| HOMEWORK_LIST_ALL[k](&homework.course_id, p)).await?;
+      if let Some(h) = with_status(k, hs).into_iter().find(|x| x.id == homework.id) { return Ok(h); }
+    }
+    Err("the homework is not found in the homework lists".into())
+  }
+
+  /// Remove the attachment of the submission of a homework, keeping its content.
+  /// It is `resubmit_homework` with `AttachmentChange::Remove`, and it is verified in the same way.
+  pub async fn delete_homework_attachment(&self, homework: &Homework) -> Result<Homework> {
+    self.resubmit_homework(homework, homework.submit_content.clone().unwrap_or_default(), AttachmentChange::Remove).await
+  }
+
+  // `deleted` removes the attachment of the previous submission
+  async fn submit(&self, student_homework: &StudentHomeworkId, content: String, deleted: bool, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    let form = || {
+      let form = Form::new().text("zynr", content.clone()).text("xszyid", student_homework.to_string()).text("isDeleted", if deleted { "1" } else { "0" });
+      form_file!(form, file.clone())
+    };
+    check_success!(a, self.text(|| self.post(HOMEWORK_SUBMIT).multipart(form())), "failed to submit homework")
   }
 
   /// Return a list of discussions of a given course. Parameter `course` refers to `Course::id`.
-  pub async fn discussion_list(&self, course: IdRef<'_>) -> Result<Vec<Discussion>> {
-    Ok(self.0.get(&DISCUSSION_LIST(course)).send().await?.json::<JsonWrapper2<JsonWrapper21<_>>>().await?.object.resultsList)
+  pub async fn discussion_list(&self, course: &CourseId) -> Result<Vec<Discussion>> {
+    self.list::<_, JsonWrapper2<JsonWrapper21<_>>>(|p| DISCUSSION_LIST(course, p)).await
+  }
+
+  /// Same as `discussion_list`, except that it returns a stream, see `notifications_stream`.
+  pub fn discussions_stream<'a>(&'a self, course: &'a CourseId) -> impl Stream<Item = Result<Discussion>> + 'a {
+    self.list_stream::<_, JsonWrapper2<JsonWrapper21<_>>>(move |p| DISCUSSION_LIST(course, p))
+  }
+
+  /// Return the discussion boards of a given course, a new discussion is created in one of them. Parameter `course` refers to `Course::id`.
+  pub async fn discussion_boards(&self, course: &CourseId) -> Result<Vec<DiscussionBoard>> {
+    Ok(self.json::<JsonWrapper2<JsonWrapper22<DiscussionBoard>>>(&DISCUSSION_BOARD_LIST(course)).await?.object.rows)
   }
 
   /// Return a list of discussion replies of a given discussion.
   /// - Parameter `course` refers to `Course::id`.
   /// - Parameter `discussion` refers to `Discussion::id`.
   /// - Parameter `discussion_board` refers to `Discussion::board_id`.
-  pub async fn discussion_replies(&self, course: IdRef<'_>, discussion: IdRef<'_>, discussion_board: IdRef<'_>) -> Result<Vec<DiscussionReply>> {
-    let res = self.0.get(&DISCUSSION_REPLIES(course, discussion, discussion_board)).send().await?.text().await?;
-    parse_discussion_replies(&res).ok_or("invalid discussion replies format".into())
+  pub async fn discussion_replies(&self, course: &CourseId, discussion: &DiscussionId, discussion_board: &BoardId) -> Result<Vec<DiscussionReply>> {
+    let url = self.hosts.learn_url(&DISCUSSION_REPLIES(course, discussion, discussion_board));
+    let res = self.text(|| self.client.get(&url)).await?;
+    parse_discussion_replies(&res).map_err(|e| e.into_error("discussion replies", url))
+  }
+
+  /// Return a list of questions in the Q&A board of a given course. Parameter `course` refers to `Course::id`.
+  pub async fn question_list(&self, course: &CourseId) -> Result<Vec<Question>> {
+    self.list::<_, JsonWrapper2<JsonWrapper21<_>>>(|p| QUESTION_LIST(course, p)).await
+  }
+
+  /// Same as `question_list`, except that it returns a stream, see `notifications_stream`.
+  pub fn questions_stream<'a>(&'a self, course: &'a CourseId) -> impl Stream<Item = Result<Question>> + 'a {
+    self.list_stream::<_, JsonWrapper2<JsonWrapper21<_>>>(move |p| QUESTION_LIST(course, p))
+  }
+
+  /// Return the content and the answers of a given question.
+  /// - Parameter `course` refers to `Course::id`.
+  /// - Parameter `question` refers to `Question::id`.
+  pub async fn question_detail(&self, course: &CourseId, question: &QuestionId) -> Result<QuestionDetail> {
+    let url = self.hosts.learn_url(&QUESTION_DETAIL(course, question));
+    let res = self.text(|| self.client.get(&url)).await?;
+    parse_question_detail(&res).map_err(|e| e.into_error("question detail", url))
+  }
+
+  /// Ask a question in the Q&A board of a given course.
+  /// - Parameter `course` refers to `Course::id`.
+  /// - Parameter `title` and `content` are the title and the content of your question.
+  /// - Parameter `file`: has the same semantics as the parameter `file` in `submit_homework`.
+  pub async fn ask_question(&self, course: &CourseId, title: String, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    let form = || {
+      let form = Form::new().text("wlkcid", course.to_string()).text("bt", title.clone()).text("nr", content.clone());
+      form_file!(form, file.clone())
+    };
+    check_success!(a, self.text(|| self.post(ASK_QUESTION).multipart(form())), "failed to ask question")
   }
 
   /// Reply to a given discussion.
@@ -178,24 +675,305 @@ impl LearnHelper {
   /// - Parameter `respondent_reply`: when exists, it refers to `DiscussionReply0::id`, meaning that you are replying to this reply.
   /// When doesn't exist, it means append a reply to the discussion.
   /// - Parameter `file`: has the same semantics as the parameter `file` in `submit_homework`.
-  pub async fn reply_discussion(&self, course: IdRef<'_>, discussion: IdRef<'_>, content: String, respondent_reply: Option<IdRef<'_>>, file: Option<(&str, Vec<u8>)>) -> Result<()> {
-    let form = Form::new().text("wlkcid", course.to_owned()).text("tltid", discussion.to_owned()).text("nr", content.to_owned());
-    let form = form_file!(form, file);
-    let form = if let Some(x) = respondent_reply { form.text("fhhid", x.to_owned()).text("_fhhid", x.to_owned()) } else { form };
-    check_success!(a, self.0.post(REPLY_DISCUSSION).multipart(form), "failed to reply discussion")
+  pub async fn reply_discussion(&self, course: &CourseId, discussion: &DiscussionId, content: String, respondent_reply: Option<&ReplyId>, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    let form = || {
+      let form = Form::new().text("wlkcid", course.to_string()).text("tltid", discussion.to_string()).text("nr", content.clone());
+      let form = form_file!(form, file.clone());
+      if let Some(x) = respondent_reply { form.text("fhhid", x.to_string()).text("_fhhid", x.to_string()) } else { form }
+    };
+    check_success!(a, self.text(|| self.post(REPLY_DISCUSSION).multipart(form())), "failed to reply discussion")
+  }
+
+  /// Replace the content of a reply that you published, the reply keeps its position in the discussion.
+  /// - Parameter `course` refers to `Course::id`.
+  /// - Parameter `discussion` refers to `Discussion::id`.
+  /// - Parameter `reply` refers to `DiscussionReply0::id`, whose `DiscussionReply0::mine` should be `true`.
+  /// - Parameter `content` is the new content of the reply.
+  /// - Parameter `file`: has the same semantics as the parameter `file` in `submit_homework`.
+  ///
+  /// Trying to edit a reply not published by yourself will generally result in an `Err`.
+  pub async fn edit_discussion_reply(&self, course: &CourseId, discussion: &DiscussionId, reply: &ReplyId, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    // the same api as replying, the existing reply is identified by `id`
+    let form = || {
+      let form = Form::new().text("wlkcid", course.to_string()).text("tltid", discussion.to_string()).text("id", reply.to_string()).text("nr", content.clone());
+      form_file!(form, file.clone())
+    };
+    check_success!(a, self.text(|| self.post(REPLY_DISCUSSION).multipart(form())), "failed to edit discussion reply")
   }
 
   /// Deleting a given discussion reply.
   /// - Parameter `course` refers to `Course::id`.
+  /// - Parameter `discussion` and `discussion_board` refer to `Discussion::id` and `Discussion::board_id`, they are used to confirm the deletion.
   /// - Parameter `reply` refers to `DiscussionReply0::id`.
-  /// 
-  /// Trying to delete a reply not published by yourself will generally result in an `Err`.
+  /// - Parameter `deadline` is how long the confirmation lasts at most, `DELETE_DR_DEADLINE` is a reasonable choice.
+  ///
+  /// Trying to delete a reply not published by yourself will generally result in `DeleteOutcome::Failed`.
+  /// The fetches of the replies that fail are retried until the deadline, except that a failed login is returned as an `Err`.
   ///
   /// Note: you can refer to `DELETE_DR_TIMEOUT` for more detail.
-  pub async fn delete_discussion_reply(&self, course: IdRef<'_>, reply: IdRef<'_>) -> Result<()> {
-    check_delete_dr_success(async move {
-      let req = self.0.post(&DELETE_DISCUSSION_REPLY(course, reply)).timeout(DELETE_DR_TIMEOUT);
-      req.send().await?.text().await
-    }.await)
+  pub async fn delete_discussion_reply(&self, course: &CourseId, discussion: &DiscussionId, discussion_board: &BoardId, reply: &ReplyId, deadline: Duration) -> Result<DeleteOutcome> {
+    let res = self.text(|| self.post(&DELETE_DISCUSSION_REPLY(course, reply)).timeout(DELETE_DR_TIMEOUT)).await;
+    if let Some(x) = check_delete_dr_success(res)? { return Ok(x); }
+    let (start, mut fetched, mut error) = (Instant::now(), false, None);
+    loop {
+      match self.discussion_replies(course, discussion, discussion_board).await {
+        Ok(rs) if !has_reply(&rs, reply) => return Ok(DeleteOutcome::Confirmed),
+        Ok(_) => fetched = true,
+        Err(e) => error = Some(check_confirm_error(e)?),
+      }
+      if start.elapsed() >= deadline { return Ok(delete_dr_outcome(fetched, error)); }
+      tokio::time::delay_for(DELETE_DR_TIMEOUT).await;
+    }
+  }
+
+  /// Create a discussion in a given course.
+  /// - Parameter `course` refers to `Course::id`.
+  /// - Parameter `board` refers to `DiscussionBoard::id`, see `discussion_boards`.
+  /// - Parameter `title` and `content` are the title and the content of the discussion.
+  /// - Parameter `file`: has the same semantics as the parameter `file` in `submit_homework`.
+  pub async fn create_discussion(&self, course: &CourseId, board: &BoardId, title: String, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    self.save_discussion(course, None, board, title, content, file).await
+  }
+
+  /// Replace the title and the content of a discussion that you published.
+  /// - Parameter `discussion` refers to `Discussion::id`.
+  /// - Parameter `board` refers to `Discussion::board_id`, or another board of the course to move the discussion into.
+  /// - The other parameters have the same semantics as the ones in `create_discussion`.
+  ///
+  /// Trying to edit a discussion not published by yourself will generally result in an `Err`.
+  pub async fn edit_discussion(&self, course: &CourseId, discussion: &DiscussionId, board: &BoardId, title: String, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    self.save_discussion(course, Some(discussion), board, title, content, file).await
   }
+
+  /// Delete a discussion that you published, together with its replies.
+  /// - Parameter `course` refers to `Course::id`.
+  /// - Parameter `discussion` refers to `Discussion::id`.
+  ///
+  /// Trying to delete a discussion not published by yourself will generally result in an `Err`.
+  pub async fn delete_discussion(&self, course: &CourseId, discussion: &DiscussionId) -> Result<()> {
+    check_success!(a, self.text(|| self.post(&DELETE_DISCUSSION(course, discussion))), "failed to delete discussion")
+  }
+
+  // `discussion` is `None` when creating a discussion
+  async fn save_discussion(&self, course: &CourseId, discussion: Option<&DiscussionId>, board: &BoardId, title: String, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    let form = || {
+      let form = Form::new().text("wlkcid", course.to_string()).text("bqid", board.to_string()).text("bt", title.clone()).text("nr", content.clone());
+      let form = form_file!(form, file.clone());
+      if let Some(x) = discussion { form.text("id", x.to_string()) } else { form }
+    };
+    check_success!(a, self.text(|| self.post(SAVE_DISCUSSION).multipart(form())), "failed to save discussion")
+  }
+
+  // `expected` is the size of the whole file when it is known in advance
+  async fn download(&self, url: &str, offset: u64, expected: Option<u64>, mut out: impl AsyncWrite + Unpin, mut progress: impl FnMut(DownloadProgress)) -> Result<Downloaded> {
+    let req = || if offset == 0 { self.client.get(url) } else { self.client.get(url).header(header::RANGE, format!("bytes={}-", offset)) };
+    let mut res = match self.response(req).await {
+      // the range starts at the end of the file, so `out` is already complete
+      Err(Error::Server { status: 416, .. }) if offset != 0 => return check_download_size(url, expected, Downloaded { file_name: None, size: offset }),
+      res => res?,
+    };
+    let mut start = download_start(res.status(), res.headers(), res.content_length(), offset);
+    let mut downloaded = offset;
+    while let Some(chunk) = res.chunk().await? {
+      let chunk = download_chunk(&mut start, &chunk);
+      out.write_all(chunk).await?;
+      downloaded += chunk.len() as u64;
+      progress(DownloadProgress { downloaded, total: start.total });
+    }
+    out.flush().await?;
+    check_download_size(url, expected.or(start.total), Downloaded { file_name: start.file_name, size: downloaded })
+  }
+
+  /// Mirror all the courses of a given semester to `root`. Parameter `semester` refers to the return value of `semester_id_list`.
+  ///
+  /// The layout of `root` is described in `sync::ItemKind`, and `root` is recommended to be used for only one semester.
+  /// A manifest (`sync::MANIFEST`) in `root` records the downloaded items,
+  /// so only the items that are new or changed since the last sync are downloaded,
+  /// and the local files of the items that no longer exist in web-learning are deleted.
+  ///
+  /// If the sync fails halfway, the next sync starts over (but the interrupted downloads are resumed).
+  pub async fn sync_semester(&self, root: impl AsRef<Path>, semester: &SemesterId) -> Result<SyncSummary> {
+    let root = root.as_ref();
+    let mut contents = Vec::new();
+    for course in self.course_list(semester).await? {
+      let (file_categories, files, homework, notifications) =
+        try_join4(self.file_categories(&course.id), self.file_list(&course.id), self.homework_list(&course.id), self.notification_list(&course.id)).await?;
+      contents.push(CourseContent { course, file_categories, files, homework, notifications });
+    }
+    let plan = sync::plan(root, &self.hosts.learn, &Manifest::load(root)?, &contents);
+    for path in &plan.removes { sync::remove(root, path)?; }
+    for (path, content) in &plan.writes { sync::write(root, path, content)?; }
+    for d in &plan.downloads {
+      let (file, offset) = sync::open_part(root, &d.path, d.restart)?;
+      let res = self.download(&d.url, offset, d.size, AllowStdIo::new(file), |_| {}).await;
+      sync::finish_part(root, &d.path, &res)?;
+      res?;
+    }
+    plan.manifest.save(root)?;
+    Ok(plan.summary)
+  }
+
+  /// Fetch everything of all the courses in a semester, which is what a "dashboard" needs. Parameter `semester` refers to the return value of `semester_id_list`.
+  ///
+  /// Unlike calling `course_list`, `notification_list`, etc. for each course, it never has more than `options.concurrency` requests in flight,
+  /// and a failed category only affects itself: the error is recorded in `CourseSnapshot::errors` and the other categories and courses are still returned.
+  /// It returns `Err` only when the course list itself cannot be fetched.
+  pub async fn semester_overview(&self, semester: &SemesterId, options: &OverviewOptions) -> Result<SemesterSnapshot> {
+    let courses = self.json::<JsonWrapper1<Course>>(&COURSE_LIST(semester)).await?.resultList;
+    let mut snapshots = courses.into_iter().map(CourseSnapshot::new).collect::<Vec<_>>();
+    // every future below has at most one request in flight, so `buffer_unordered` bounds the number of requests in flight
+    let limit = options.concurrency.max(1);
+    let parts = overview_parts(snapshots.len(), options);
+    let mut res = stream::iter(parts.iter().enumerate()).map(|(j, &(i, category, k))| {
+      let course = &snapshots[i].course.id;
+      async move { (j, self.overview_part(course, category, k).await) }
+    }).buffer_unordered(limit).collect::<Vec<_>>().await;
+    res.sort_by_key(|x| x.0); // keep the order of the 3 homework lists
+    for (j, res) in res { overview_merge(&mut snapshots[parts[j].0], parts[j].1, res); }
+    let details = snapshots.iter_mut().enumerate().flat_map(|(i, s)| {
+      let CourseSnapshot { notifications, homework, .. } = s;
+      let n = notifications.iter_mut().flatten().filter(|x| x.attachment_name.is_some())
+        .map(move |x| Either::Left(async move { (i, Category::Notifications, self.notification_attachment(x).await) }));
+      let h = homework.iter_mut().flatten().map(move |x| Either::Right(async move { (i, Category::Homework, self.homework_detail(x).await) }));
+      n.chain(h)
+    });
+    let errors = stream::iter(details).buffer_unordered(limit).filter_map(|(i, category, res)| ready(res.err().map(|e| (i, category, e)))).collect::<Vec<_>>().await;
+    for (i, category, e) in errors { snapshots[i].fail(category, e); }
+    Ok(SemesterSnapshot { semester: semester.clone(), courses: snapshots })
+  }
+
+  /// Poll a semester with `semester_overview` every `interval` (the first poll is immediate), and emit what changed since the last poll as events.
+  /// - Parameter `semester` refers to the return value of `semester_id_list`.
+  /// - Parameter `options` is passed to `semester_overview`, the categories not requested in it are not watched and emit no events.
+  /// - Parameter `store` keeps the state of the last poll. With a persistent one, for example, `watch::FileStore`,
+  ///   a restarted watch continues from where it stopped, instead of emitting the old events again.
+  ///
+  /// A failed poll (or a failed category of a course) emits `Event::Failed`, and the watch goes on.
+  /// The stream never ends, drop it to stop watching.
+  pub fn watch<'a>(&'a self, semester: &'a SemesterId, interval: Duration, options: &'a OverviewOptions, store: impl StateStore + 'a) -> impl Stream<Item = Event> + 'a {
+    stream::unfold((store, VecDeque::new(), true), move |(mut store, mut events, mut first)| async move {
+      while events.is_empty() {
+        if !first { tokio::time::delay_for(interval).await; }
+        first = false;
+        events = watch::poll(&mut store, self.semester_overview(semester, options).await).into();
+      }
+      Some((events.pop_front().unwrap(), (store, events, first)))
+    })
+  }
+
+  // sends the requests one by one, `k` selects the list in `HOMEWORK_LIST_ALL` for `Category::Homework`
+  async fn overview_part(&self, course: &CourseId, category: Category, k: usize) -> Result<OverviewPart> {
+    Ok(match category {
+      Category::TimeLocation => OverviewPart::TimeLocation(self.json(&COURSE_TIME_LOCATION(course)).await?),
+      Category::Notifications => OverviewPart::Notifications(self.list::<Notification, NotificationPage>(|p| NOTIFICATION_LIST(course, p)).await?),
+      Category::Files => OverviewPart::Files(self.file_list(course).await?),
+      Category::Homework => OverviewPart::Homework(with_status(k, self.list::<_, JsonWrapper2<JsonWrapper20<_>>>(|p| HOMEWORK_LIST_ALL[k](course, p)).await?)),
+      Category::Discussions => OverviewPart::Discussions(self.discussion_list(course).await?),
+    })
+  }
+
+  async fn notification_attachment(&self, x: &mut Notification) -> Result<()> {
+    x.attachment = if let Some(name) = x.attachment_name.clone() {
+      let url = self.hosts.learn_url(&NOTIFICATION_DETAIL(&x.id, &x.course_id));
+      let res = self.text(|| self.client.get(&url)).await?;
+      let url = parse_notification_attachment(&res, &self.hosts.learn).map_err(|e| e.into_error("notification detail", url))?;
+      Some(Attachment { name, url })
+    } else { None };
+    OK
+  }
+
+  async fn homework_detail(&self, x: &mut Homework) -> Result<()> {
+    let url = self.hosts.learn_url(&HOMEWORK_DETAIL(&x.course_id, &x.id, &x.student_homework_id));
+    let res = self.text(|| self.client.get(&url)).await?;
+    x.detail = parse_homework_detail(&res, &self.hosts.learn).map_err(|e| e.into_error("homework detail", url))?;
+    OK
+  }
+
+  // `url` maps a page number to the path of the page
+  fn pages<'a, T: 'a, W: Page<T> + 'a>(&'a self, url: impl Fn(usize) -> String + 'a) -> impl Stream<Item = Result<Vec<T>>> + 'a {
+    stream::unfold(Pager::default(), move |mut pager| {
+      let path = pager.next(&url);
+      async move {
+        let path = path?;
+        let text = self.text(|| self.get(&path)).await;
+        Some((pager.feed::<T, W>(self.hosts.learn_url(&path), text), pager))
+      }
+    })
+  }
+
+  fn list_stream<'a, T: 'a, W: Page<T> + 'a>(&'a self, url: impl Fn(usize) -> String + 'a) -> impl Stream<Item = Result<T>> + 'a {
+    self.pages::<T, W>(url).flat_map(|x| stream::iter(page_items(x)))
+  }
+
+  async fn list<T, W: Page<T>>(&self, url: impl Fn(usize) -> String) -> Result<Vec<T>> {
+    self.pages::<T, W>(url).try_concat().await
+  }
+
+  fn get(&self, path: &str) -> RequestBuilder { self.client.get(&self.hosts.learn_url(path)) }
+
+  // `req` builds the request, it may be called twice because of re-login
+  async fn text(&self, req: impl Fn() -> RequestBuilder) -> Result<String> {
+    Ok(self.response(req).await?.text().await?)
+  }
+
+  async fn response(&self, req: impl Fn() -> RequestBuilder) -> Result<Response> {
+    let generation = self.session.lock().unwrap().generation;
+    match self.response_once(req()).await {
+      Err(Error::SessionExpired) if self.credentials.is_some() => {
+        self.relogin(generation).await?;
+        self.response_once(req()).await
+      }
+      res => res,
+    }
+  }
+
+  // login again, unless another request has done it since the session of `generation` is used
+  async fn relogin(&self, generation: u64) -> Result<()> {
+    let _guard = self.relogin.lock().await;
+    if self.session.lock().unwrap().generation != generation { return Ok(()); }
+    let (username, password) = self.credentials.as_ref().unwrap().get().ok_or(Error::SessionExpired)?;
+    self.login_as(&username, &password).await
+  }
+
+  async fn response_once(&self, req: RequestBuilder) -> Result<Response> {
+    let res = self.send(req).await?;
+    check_status(res.status(), res.url())?;
+    Ok(res)
+  }
+
+  // send the request with the session cookies, and follow the redirections
+  async fn send(&self, req: RequestBuilder) -> Result<Response> {
+    // don't hold the lock across the request
+    let cookie = self.session.lock().unwrap().cookie_header();
+    let mut res = req.header(header::COOKIE, cookie).send().await?;
+    for _ in 0..MAX_REDIRECTS {
+      if self.hosts.is_learn(res.url()) { self.session.lock().unwrap().store(res.cookies()); }
+      let url = match redirect_target(&self.hosts, res.status(), res.url(), res.headers())? { Some(url) => url, None => return Ok(res) };
+      let cookie = if self.hosts.is_learn(&url) { self.session.lock().unwrap().cookie_header() } else { String::new() };
+      res = self.client.get(url).header(header::COOKIE, cookie).send().await?;
+    }
+    Err("too many redirections".into())
+  }
+
+  async fn json<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+    parse_json(self.hosts.learn_url(path), &self.text(|| self.get(path)).await?)
+  }
+
+  async fn login_as(&self, username: &str, password: &str) -> Result<()> {
+    let params = [("i_user", username), ("i_pass", password), ("atOnce", "true")];
+    let res = self.client.post(&self.hosts.id_url(LOGIN)).form(&params).send().await?.text().await?;
+    let ticket_start = res.find("ticket=").ok_or(Error::Auth)? + 7; // 7 == "ticket=".len()
+    let ticket_len = res[ticket_start..].find("\"").ok_or(Error::Auth)?;
+    let generation = self.session.lock().unwrap().generation;
+    *self.session.lock().unwrap() = Session { generation, ..Session::default() };
+    if let Err(e) = self.send(self.post(&AUTH_ROAM(&res[ticket_start..ticket_start + ticket_len]))).await {
+      return Err(if let Error::SessionExpired = e { Error::Auth } else { e });
+    }
+    let mut session = self.session.lock().unwrap();
+    session.login_time = Some(Utc::now());
+    session.generation += 1; // only after the new session is complete
+    Ok(())
+  }
+
+  fn post(&self, path: &str) -> RequestBuilder { self.client.post(&self.hosts.learn_url(path)) }
 }
\ No newline at end of file
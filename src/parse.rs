@@ -1,7 +1,7 @@
-use chrono::{NaiveDateTime, format::ParseResult};
-use serde::{Deserialize, Deserializer, de::Error};
+use chrono::{NaiveDateTime, Weekday, format::ParseResult};
+use serde::{Deserialize, Deserializer, de::{Error, DeserializeOwned}};
 use select::{document::Document, node::Node, predicate::{Predicate, Attr as A, Class as C, Name as N}};
-use crate::{urls::*, types::{HomeworkDetail, DiscussionReply0, DiscussionReply}};
+use crate::{types::{self, HomeworkDetail, Attachment, DiscussionReply0, DiscussionReply, QuestionDetail, ReplyId, TimeSlot, Weeks}};
 
 #[derive(Deserialize)]
 pub struct JsonWrapper1<T> { pub resultList: Vec<T> }
@@ -15,104 +15,417 @@ pub struct JsonWrapper20<T> { pub aaData: Vec<T> }
 #[derive(Deserialize)]
 pub struct JsonWrapper21<T> { pub resultsList: Vec<T> }
 
-pub fn parse_homework_detail(html: &str) -> Option<HomeworkDetail> {
-  let d = Document::from(html);
-  let mut file_div = d.find(C("list").and(C("fujian")).and(C("clearfix")));
-  fn name_url(n: Option<Node>) -> Option<(String, String)> {
-    for n in n?.find(C("ftitle")) {
-      let n = n.children().nth(1)?;
-      let name = n.children().next()?.as_text()?.to_owned();
-      let href = n.attr("href")?;
-      let url_start = href.find("downloadUrl=")? + 12;
-      return Some((name, PREFIX.to_owned() + &href[url_start..]));
+#[derive(Deserialize)]
+pub struct JsonWrapper22<T> { pub rows: Vec<T> }
+
+#[derive(Deserialize)]
+pub struct JsonWrapper3<T> { pub result: T }
+
+// a page of a paginated list api, the wrappers differ between the apis
+pub trait Page<T>: DeserializeOwned {
+  fn items(self) -> Vec<T>;
+}
+
+impl<T: DeserializeOwned> Page<T> for JsonWrapper2<Vec<T>> {
+  fn items(self) -> Vec<T> { self.object }
+}
+
+impl<T: DeserializeOwned> Page<T> for JsonWrapper2<JsonWrapper20<T>> {
+  fn items(self) -> Vec<T> { self.object.aaData }
+}
+
+impl<T: DeserializeOwned> Page<T> for JsonWrapper2<JsonWrapper21<T>> {
+  fn items(self) -> Vec<T> { self.object.resultsList }
+}
+
+// the step that a scraper failed at, and the html around it
+// the caller knows which page it is and where it comes from, and turns it into a `types::Error::Parse`
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+  pub step: &'static str,
+  pub snippet: String,
+}
+
+impl ParseError {
+  pub fn into_error(self, page: &'static str, url: String) -> types::Error {
+    types::Error::Parse { page, url, step: self.step, snippet: self.snippet }
+  }
+}
+
+trait Step<T> {
+  fn step(self, step: &'static str, around: Node) -> Result<T, ParseError>;
+}
+
+impl<T> Step<T> for Option<T> {
+  fn step(self, step: &'static str, around: Node) -> Result<T, ParseError> {
+    self.ok_or_else(|| ParseError { step, snippet: excerpt(&around.html()) })
+  }
+}
+
+// the first 200 characters of `s` with consecutive whitespaces collapsed, long enough to identify the problem in a log line
+pub fn excerpt(s: &str) -> String {
+  const LEN: usize = 200;
+  let mut ret = String::new();
+  for (i, w) in s.split_whitespace().enumerate() {
+    if i != 0 { ret.push(' '); }
+    ret += w;
+    if ret.chars().count() > LEN {
+      return ret.chars().take(LEN).collect::<String>() + "...";
     }
-    None
   }
-  Some(HomeworkDetail {
-    description: d.find(C("list").and(C("calendar")).and(C("clearfix")).descendant(C("fl").and(C("right"))).descendant(C("c55"))).next()?.inner_html(),
-    attachment_name_url: name_url(file_div.next()),
-    submit_attachment_name_url: name_url(file_div.nth(1)),
-    grade_attachment_name_url: name_url(file_div.next()),
+  ret
+}
+
+pub fn parse_json<T: DeserializeOwned>(url: String, text: &str) -> types::Result<T> {
+  serde_json::from_str(text).map_err(|source| types::Error::Json { endpoint: url, body_excerpt: excerpt(text), source })
+}
+
+// `prefix` is `Hosts::learn`, the attachment urls in the page are relative to it
+pub fn parse_homework_detail(html: &str, prefix: &str) -> Result<HomeworkDetail, ParseError> {
+  let d = Document::from(html);
+  let root = d.nth(0).unwrap();
+  let mut file_div = d.find(C("list").and(C("fujian")).and(C("clearfix")));
+  let attachment = |n: Option<Node>| -> Result<Option<Attachment>, ParseError> {
+    let n = match n.and_then(|n| n.find(C("ftitle")).next()) { Some(n) => n, None => return Ok(None) };
+    let a = n.children().nth(1).step("attachment link", n)?;
+    let name = a.children().next().and_then(|x| x.as_text()).step("attachment name", a)?.to_owned();
+    let href = a.attr("href").step("attachment href", a)?;
+    let url_start = href.find("downloadUrl=").step("attachment download url", a)? + 12;
+    Ok(Some(Attachment { name, url: prefix.to_owned() + &href[url_start..] }))
+  };
+  let calendar = C("list").and(C("calendar")).and(C("clearfix"));
+  let right = calendar.descendant(C("fl").and(C("right")));
+  // the snippet of a failure is the innermost container of the description that is found
+  let around = d.find(right).next().or_else(|| d.find(calendar).next()).unwrap_or(root);
+  Ok(HomeworkDetail {
+    description: d.find(right.descendant(C("c55"))).next().step("homework description", around)?.inner_html(),
+    attachment: attachment(file_div.next())?,
+    submit_attachment: attachment(file_div.nth(1))?,
+    grade_attachment: attachment(file_div.next())?,
   })
 }
 
-pub fn parse_discussion_replies(html: &str) -> Option<Vec<DiscussionReply>> {
+// `prefix` has the same meaning as in `parse_homework_detail`
+pub fn parse_notification_attachment(html: &str, prefix: &str) -> Result<String, ParseError> {
+  let d = Document::from(html);
+  let a = d.find(N("a").and(C("ml-10"))).next().step("attachment link", d.nth(0).unwrap())?;
+  Ok(prefix.to_owned() + a.attr("href").step("attachment href", a)?)
+}
+
+pub fn parse_discussion_replies(html: &str) -> Result<Vec<DiscussionReply>, ParseError> {
   let d = Document::from(html);
   let mut ret = Vec::new();
   for (idx, n) in d.find(C("list").and(C("lists")).and(C("clearfix"))).enumerate() {
-    let id = n.attr("id").and_then(|x| Some(x.get("item_".len()..)?.to_owned()));
-    let content = n.find(C("right")).next()?;
+    let id = n.attr("id").and_then(|x| Some(ReplyId::from(x.get("item_".len()..)?)));
+    let content = n.find(C("right")).next().step("reply content", n)?;
+    // the content of the first reply is the paragraphs directly in `content`, beside the publish time
     let content1 = if idx == 0 {
-      let mut s = String::new();
-      for ch in content.find(N("p")) {
-        if let Some(x) = ch.children().next().and_then(|x| x.as_text()) { s += x; }
-      }
-      s
-    } else { content.find(A("name", "p_nr")).next()?.inner_html() };
-    let author = n.find(C("name")).next()?.inner_html();
-    let time = n.find(C("time")).next()?.children().nth(1)?;
-    let publish_time = date_time_hm(if idx == 0 { &time.children().next()?.as_text()? } else {
-      &time.as_text()?.get("楼：".len()..)?
-    }).ok()?;
+      content.children().filter(|x| x.name() == Some("p")).map(|x| x.html()).collect()
+    } else { content.find(A("name", "p_nr")).next().step("reply text", content)?.inner_html() };
+    let author = n.find(C("name")).next().step("reply author", n)?.inner_html();
+    let time = n.find(C("time")).next().and_then(|x| x.children().nth(1)).step("reply time", n)?;
+    let publish_time = if idx == 0 { time.children().next().and_then(|x| x.as_text()) } else {
+      time.as_text().and_then(|x| x.get("楼：".len()..))
+    }.and_then(|x| date_time_hm(x).ok()).step("reply publish time", n)?;
+    // only the replies of the current user have the delete link, and the edit link beside it
+    let mine = n.find(C("info")).next().map_or(false, |x| x.find(C("del")).next().is_some());
     let mut replies = Vec::new();
     if let Some(reply) = content.find(C("huifu_cont").and(C("panel"))).next() {
       for item in reply.find(C("item")) {
-        let id = item.attr("id").and_then(|x| Some(x.get("item_".len()..)?.to_owned())); // actually it must be Some(_)
-        let content = item.find(A("name", "p_nr")).next()?;
-        let author = content.prev()?.prev()?.children().next()?.as_text()?;
-        let author = author.get(..author.len() - "：".len())?.to_owned();
-        let publish_time = date_time_hm(item.find(C("time")).next()?.children().next()?.as_text()?).ok()?;
-        replies.push(DiscussionReply0 { id, author, publish_time, content: content.inner_html(), replies: () });
+        let id = item.attr("id").and_then(|x| Some(ReplyId::from(x.get("item_".len()..)?))); // actually it must be Some(_)
+        let content = item.find(A("name", "p_nr")).next().step("sub-reply text", item)?;
+        let author = content.prev().and_then(|x| x.prev()).and_then(|x| x.children().next()).and_then(|x| x.as_text())
+          .and_then(|x| x.get(..x.len().checked_sub("：".len())?)).step("sub-reply author", item)?.to_owned();
+        let publish_time = item.find(C("time")).next().and_then(|x| x.children().next()).and_then(|x| x.as_text())
+          .and_then(|x| date_time_hm(x).ok()).step("sub-reply publish time", item)?;
+        let mine = item.find(C("del")).next().is_some();
+        replies.push(DiscussionReply0 { id, author, publish_time, content: content.inner_html(), mine, replies: () });
       }
     }
-    ret.push(DiscussionReply0 { id, author, publish_time, content: content1, replies })
+    ret.push(DiscussionReply0 { id, author, publish_time, content: content1, mine, replies })
+  }
+  Ok(ret)
+}
+
+// the page has the same structure as the discussion replies, the first "reply" is the question itself
+pub fn parse_question_detail(html: &str) -> Result<QuestionDetail, ParseError> {
+  let mut replies = parse_discussion_replies(html)?.into_iter();
+  let question = replies.next().ok_or_else(|| ParseError { step: "question content", snippet: excerpt(html) })?;
+  Ok(QuestionDetail { content: question.content, answers: replies.collect() })
+}
+
+// the file name in a "Content-Disposition" header, for example, `attachment; filename="a.pdf"`
+// `filename*=UTF-8''...` (RFC 5987) is preferred if it exists, otherwise `filename=` may contain raw utf-8 bytes
+pub fn parse_content_disposition(value: &[u8]) -> Option<String> {
+  let value = String::from_utf8_lossy(value);
+  let param = |name: &str| value.split(';').filter_map(|x| {
+    let mut kv = x.trim().splitn(2, '=');
+    if kv.next()?.trim().eq_ignore_ascii_case(name) { Some(kv.next()?.trim().trim_matches('"').to_owned()) } else { None }
+  }).next().filter(|x| !x.is_empty());
+  let extended = param("filename*").and_then(|x| {
+    let (charset, x) = x.split_at(x.find('\'')?);
+    if !charset.eq_ignore_ascii_case("utf-8") { return None; }
+    String::from_utf8(percent_decode(&x[x[1..].find('\'')? + 2..])?).ok()
+  });
+  extended.or_else(|| param("filename"))
+}
+
+fn percent_decode(s: &str) -> Option<Vec<u8>> {
+  let (s, mut ret) = (s.as_bytes(), Vec::new());
+  let mut i = 0;
+  while i < s.len() {
+    if s[i] == b'%' {
+      ret.push(u8::from_str_radix(std::str::from_utf8(s.get(i + 1..i + 3)?).ok()?, 16).ok()?);
+      i += 3;
+    } else {
+      ret.push(s[i]);
+      i += 1;
+    }
   }
   Some(ret)
 }
 
+// "星期一第2节(全周)，六教6A017", "星期三第1-2节(第1-8,10周)" or "星期五第6节(双周)", the classroom may be missing
+pub fn parse_time_slot(s: &str) -> Option<TimeSlot> {
+  let s = s.trim().trim_start_matches("星期");
+  let mut chars = s.chars();
+  let weekday = match chars.next()? {
+    '一' => Weekday::Mon, '二' => Weekday::Tue, '三' => Weekday::Wed, '四' => Weekday::Thu,
+    '五' => Weekday::Fri, '六' => Weekday::Sat, '日' | '天' => Weekday::Sun, _ => return None,
+  };
+  let s = chars.as_str();
+  if !s.starts_with('第') { return None; }
+  let (periods, s) = s['第'.len_utf8()..].split_at(s.find('节')? - '第'.len_utf8());
+  let (first_period, last_period) = parse_range1(periods)?;
+  let s = s['节'.len_utf8()..].trim_start_matches(|c| c == '(' || c == '（');
+  let end = s.find(|c| c == ')' || c == '）')?;
+  let weeks = match &s[..end] {
+    "全周" => Weeks::All,
+    "单周" => Weeks::Odd,
+    "双周" => Weeks::Even,
+    "前八周" => Weeks::Explicit(vec![(1, 8)]),
+    "后八周" => Weeks::Explicit(vec![(9, 16)]),
+    w if w.starts_with('第') && w.ends_with('周') => {
+      let w = &w['第'.len_utf8()..w.len() - '周'.len_utf8()];
+      Weeks::Explicit(w.split(|c| c == ',' || c == '，' || c == '、').map(parse_range1).collect::<Option<_>>()?)
+    }
+    _ => return None,
+  };
+  let classroom = s[end..].chars().skip(1).collect::<String>();
+  let classroom = classroom.trim_start_matches(|c| c == ',' || c == '，').trim();
+  Some(TimeSlot { weekday, first_period, last_period, weeks, classroom: if classroom.is_empty() { None } else { Some(classroom.to_owned()) } })
+}
+
+// a notification in web-learning json, whose content is base64 encoded,
+// while `Notification` itself deserializes the plain content, so that the serialized one is read back as is
+#[derive(Deserialize)]
+pub struct WebNotification {
+  #[serde(flatten)] notification: types::Notification,
+  #[serde(rename = "ggnr", deserialize_with = "base64_string")] content: String,
+}
+
+pub type NotificationPage = JsonWrapper2<JsonWrapper20<WebNotification>>;
+
+impl Page<types::Notification> for NotificationPage {
+  fn items(self) -> Vec<types::Notification> {
+    self.object.aaData.into_iter().map(|x| types::Notification { content: x.content, ..x.notification }).collect()
+  }
+}
+
+// "2" or "1-2", both ends are inclusive
+fn parse_range(s: &str) -> Option<(u8, u8)> {
+  let mut it = s.splitn(2, '-');
+  let first = it.next()?.trim().parse().ok()?;
+  let last = match it.next() { Some(x) => x.trim().parse().ok()?, None => first };
+  if first <= last { Some((first, last)) } else { None }
+}
+
+// weeks and periods count from 1
+fn parse_range1(s: &str) -> Option<(u8, u8)> { parse_range(s).filter(|x| x.0 >= 1) }
+
 fn date_time_hm(s: &str) -> ParseResult<NaiveDateTime> { NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M") }
 
-pub fn date_time<'d, D>(d: D) -> Result<NaiveDateTime, D::Error> where D: Deserializer<'d> {
-  date_time_hm(<&str>::deserialize(d)?).map_err(Error::custom)
+// the formats in web-learning, with or without seconds, and the format that `NaiveDateTime` serializes to,
+// so that the serialized types can be deserialized back (so do the other deserializers below)
+fn any_date_time(s: &str) -> ParseResult<NaiveDateTime> {
+  date_time_hm(s).or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")).or_else(|_| s.parse())
 }
 
-pub fn date_time1<'d, D>(d: D) -> Result<NaiveDateTime, D::Error> where D: Deserializer<'d> {
-  NaiveDateTime::parse_from_str(<&str>::deserialize(d)?, "%Y-%m-%d %H:%M:%S").map_err(Error::custom)
+pub fn date_time<'d, D>(d: D) -> Result<NaiveDateTime, D::Error> where D: Deserializer<'d> {
+  any_date_time(&String::deserialize(d)?).map_err(Error::custom)
 }
 
-// there is indeed some duplication, a better approach is to use the newtype pattern and define wrapper class for NaiveDateTime
-// but that would involve more boilerplate code, and is harder to use
 pub fn option_date_time<'d, D>(d: D) -> Result<Option<NaiveDateTime>, D::Error> where D: Deserializer<'d> {
-  match <Option<&str>>::deserialize(d)? {
+  match <Option<String>>::deserialize(d)?.as_deref() {
     Some("") | None => Ok(None),
-    Some(s) => NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").map_err(Error::custom).map(Some)
+    Some(s) => any_date_time(s).map_err(Error::custom).map(Some)
   }
 }
 
-pub fn option_date_time1<'d, D>(d: D) -> Result<Option<NaiveDateTime>, D::Error> where D: Deserializer<'d> {
-  match <Option<&str>>::deserialize(d)? {
-    Some("") | None => Ok(None),
-    Some(s) => NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").map_err(Error::custom).map(Some)
-  }
-}
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyBool { Bool(bool), Int(u32), Str(String) }
 
-pub fn str_to_bool1<'d, D>(d: D) -> Result<bool, D::Error> where D: Deserializer<'d> {
-  Ok(<&str>::deserialize(d)? == "是")
+// `s` is the string that means `true`
+fn any_bool<'d, D>(d: D, s: &str) -> Result<bool, D::Error> where D: Deserializer<'d> {
+  Ok(match AnyBool::deserialize(d)? { AnyBool::Bool(x) => x, AnyBool::Int(x) => x != 0, AnyBool::Str(x) => x == s })
 }
 
-pub fn str_to_bool2<'d, D>(d: D) -> Result<bool, D::Error> where D: Deserializer<'d> {
-  Ok(<&str>::deserialize(d)? == "1")
-}
+pub fn str_to_bool1<'d, D>(d: D) -> Result<bool, D::Error> where D: Deserializer<'d> { any_bool(d, "是") }
+
+pub fn str_to_bool2<'d, D>(d: D) -> Result<bool, D::Error> where D: Deserializer<'d> { any_bool(d, "1") }
+
+pub fn int_to_bool<'d, D>(d: D) -> Result<bool, D::Error> where D: Deserializer<'d> { any_bool(d, "1") }
 
 pub fn base64_string<'d, D>(d: D) -> Result<String, D::Error> where D: Deserializer<'d> {
-  let s = <Option<&str>>::deserialize(d)?.unwrap_or("");
-  Ok(String::from_utf8(base64::decode(s).map_err(Error::custom)?).map_err(Error::custom)?)
+  let s = <Option<String>>::deserialize(d)?.unwrap_or_default();
+  String::from_utf8(base64::decode(&s).map_err(Error::custom)?).map_err(Error::custom)
 }
 
-pub fn nonempty_string<'d, D>(d: D) -> Result<Option<String>, D::Error> where D: Deserializer<'d> {
-  Ok(<Option<String>>::deserialize(d)?.filter(|s| !s.is_empty()))
+pub fn nonempty_string<'d, D, T: From<String>>(d: D) -> Result<Option<T>, D::Error> where D: Deserializer<'d> {
+  Ok(<Option<String>>::deserialize(d)?.filter(|s| !s.is_empty()).map(T::from))
 }
 
-pub fn int_to_bool<'d, D>(d: D) -> Result<bool, D::Error> where D: Deserializer<'d> {
-  Ok(u32::deserialize(d)? != 0)
+// the pages in `fixtures` follow the structure of web-learning pages, add a new one whenever web-learning changes its layout
+#[cfg(test)]
+mod tests {
+  use chrono::NaiveDate;
+  use super::*;
+
+  const PREFIX: &str = "https://learn.tsinghua.edu.cn";
+
+  fn attachment(name: &str, id: &str) -> Option<Attachment> {
+    Some(Attachment { name: name.to_owned(), url: format!("{}/b/wlxt/kczy/zy/student/downloadFile/c1/{}", PREFIX, id) })
+  }
+
+  fn reply<R>(id: Option<&str>, author: &str, time: (u32, u32, u32, u32), content: &str, replies: R) -> DiscussionReply0<R> {
+    let publish_time = NaiveDate::from_ymd_opt(2020, time.0, time.1).unwrap().and_hms_opt(time.2, time.3, 0).unwrap();
+    DiscussionReply0 { id: id.map(ReplyId::from), author: author.to_owned(), publish_time, content: content.to_owned(), mine: false, replies }
+  }
+
+  fn mine<R>(r: DiscussionReply0<R>) -> DiscussionReply0<R> { DiscussionReply0 { mine: true, ..r } }
+
+  #[test]
+  fn homework_detail() {
+    let cases = [
+      (include_str!("../fixtures/homework_detail_new.html"), HomeworkDetail {
+        description: "<p>实现一个简单的词法分析器。</p>".to_owned(),
+        attachment: attachment("pa1.pdf", "a1"),
+        submit_attachment: None,
+        grade_attachment: None,
+      }),
+      (include_str!("../fixtures/homework_detail_submitted.html"), HomeworkDetail {
+        description: "<p>实现语法分析器。</p>".to_owned(),
+        attachment: None,
+        submit_attachment: attachment("pa2.zip", "a2"),
+        grade_attachment: None,
+      }),
+      (include_str!("../fixtures/homework_detail_graded.html"), HomeworkDetail {
+        description: "熟悉实验环境，<a href=\"https://decaf-lang.github.io\">阅读文档</a>。".to_owned(),
+        attachment: attachment("pa0.pdf", "a3"),
+        submit_attachment: attachment("pa0-report.pdf", "a4"),
+        grade_attachment: attachment("pa0-comment.pdf", "a5"),
+      }),
+      (include_str!("../fixtures/homework_detail_no_attachment.html"), HomeworkDetail::default()),
+    ];
+    for (html, expect) in cases.iter() {
+      assert_eq!(parse_homework_detail(html, PREFIX).as_ref(), Ok(expect));
+    }
+    assert_eq!(parse_homework_detail(include_str!("../fixtures/login_page.html"), PREFIX).unwrap_err().step, "homework description");
+    let html = r#"<div class="header">导航</div><div class="list calendar clearfix"><div class="fl right"><p>改版后的说明</p></div></div>"#;
+    assert_eq!(parse_homework_detail(html, PREFIX).unwrap_err().snippet, r#"<div class="fl right"><p>改版后的说明</p></div>"#);
+  }
+
+  #[test]
+  fn discussion_replies() {
+    let cases = [
+      (include_str!("../fixtures/discussion_replies.html"), vec![
+        reply(None, "张三", (3, 2, 10, 0), "<p>PA1的截止时间是什么时候？</p><p>谢谢！</p>", vec![]),
+        reply(Some("r1"), "李四", (3, 2, 11, 0), "3月15日。", vec![
+          reply(Some("r2"), "张三", (3, 2, 12, 0), "好的，谢谢。", ()),
+          mine(reply(Some("r3"), "王五", (3, 2, 12, 30), "收到", ())),
+        ]),
+        reply(Some("r4"), "李四", (3, 3, 11, 30), "补充：<a href=\"/f/wlxt/kczy/zy/student/viewCj?wlkcid=c1&amp;zyid=h1\">作业链接</a>", vec![]),
+      ]),
+      (include_str!("../fixtures/discussion_replies_deleted_author.html"), vec![
+        mine(reply(None, "王五", (3, 4, 9, 0), "<p>有人组队吗？</p>", vec![])),
+        reply(Some("r5"), "", (3, 4, 10, 0), "我来", vec![reply(Some("r6"), "", (3, 4, 10, 5), "+1", ())]),
+      ]),
+      (include_str!("../fixtures/discussion_replies_publisher_only.html"), vec![
+        reply(None, "王五", (3, 4, 9, 0), "<p>无人回复的讨论。</p><p><img src=\"/b/wlxt/bbs/img/1.png\"></p>", vec![]),
+      ]),
+    ];
+    for (html, expect) in cases.iter() {
+      assert_eq!(parse_discussion_replies(html).as_ref(), Ok(expect));
+    }
+    assert_eq!(parse_discussion_replies(include_str!("../fixtures/login_page.html")), Ok(vec![]));
+    let broken = include_str!("../fixtures/discussion_replies.html").replace("<span class=\"time\">2020-03-02 12:00</span>", "");
+    assert_eq!(parse_discussion_replies(&broken).unwrap_err().step, "sub-reply publish time");
+  }
+
+  #[test]
+  fn notification_attachment() {
+    let url = parse_notification_attachment(include_str!("../fixtures/notification_detail.html"), PREFIX);
+    assert_eq!(url, Ok(format!("{}/b/wlxt/kj/wlkc_kjxxb/student/downloadFile?sfgk=0&wjid=a6", PREFIX)));
+    assert_eq!(parse_notification_attachment(include_str!("../fixtures/login_page.html"), PREFIX).unwrap_err().step, "attachment link");
+  }
+
+  #[test]
+  fn question_detail() {
+    let q = parse_question_detail(include_str!("../fixtures/question_detail_unanswered.html")).unwrap();
+    assert_eq!((q.content.as_str(), q.answers.len()), ("<p>期中考试的范围是什么？</p>", 0));
+    assert_eq!(parse_question_detail(include_str!("../fixtures/login_page.html")).unwrap_err().step, "question content");
+  }
+
+  #[test]
+  fn notification_content() {
+    let json = include_str!("../fixtures/notifications.json");
+    let ns: Vec<types::Notification> = serde_json::from_str::<NotificationPage>(json).unwrap().items();
+    assert_eq!(ns[0].content, "<p>第一次课在六教6A017。</p>");
+    // malformed base64 from web-learning is an error, while the serialized content is read back as is, even if it looks like base64
+    assert!(serde_json::from_str::<NotificationPage>(&json.replace("PHA+56ys", "!!")).is_err());
+    let n = types::Notification { content: "YWJj".to_owned(), ..ns[0].clone() };
+    assert_eq!(serde_json::from_str::<types::Notification>(&serde_json::to_string(&n).unwrap()).unwrap(), n);
+  }
+
+  #[test]
+  fn content_disposition() {
+    let cases: [(&[u8], Option<&str>); 6] = [
+      (b"attachment; filename=\"a.pdf\"", Some("a.pdf")),
+      (b"attachment;filename=a.pdf", Some("a.pdf")),
+      ("attachment; filename=\"第一讲.pdf\"".as_bytes(), Some("第一讲.pdf")),
+      (b"attachment; filename=\"a.pdf\"; filename*=UTF-8''%E7%AC%AC%E4%B8%80%E8%AE%B2.pdf", Some("第一讲.pdf")),
+      (b"attachment; filename*=utf-8'zh'%E7%AC%AC%zz.pdf; filename=b.pdf", Some("b.pdf")),
+      (b"inline", None),
+    ];
+    for (value, expect) in cases.iter() {
+      assert_eq!(parse_content_disposition(value).as_deref(), *expect);
+    }
+  }
+
+  #[test]
+  fn time_slot() {
+    let slot = |weekday, first_period, last_period, weeks, classroom: Option<&str>| Some(TimeSlot { weekday, first_period, last_period, weeks, classroom: classroom.map(str::to_owned) });
+    let cases = [
+      ("星期一第2节(全周)，六教6A017", slot(Weekday::Mon, 2, 2, Weeks::All, Some("六教6A017"))),
+      ("星期三第4节(单周)，六教6A017", slot(Weekday::Wed, 4, 4, Weeks::Odd, Some("六教6A017"))),
+      ("星期日第1-2节（双周）,三教1102", slot(Weekday::Sun, 1, 2, Weeks::Even, Some("三教1102"))),
+      ("星期五第6节(前八周)", slot(Weekday::Fri, 6, 6, Weeks::Explicit(vec![(1, 8)]), None)),
+      ("星期二第3节(第1-4,6，9-10周)，", slot(Weekday::Tue, 3, 3, Weeks::Explicit(vec![(1, 4), (6, 6), (9, 10)]), None)),
+      ("星期八第3节(全周)，六教6A017", None),
+      ("星期二第3-1节(全周)", None),
+      ("星期二第0节(全周)", None),
+      ("星期二第3节(第0-4周)", None),
+      ("星期二第3节(隔周)", None),
+      ("待定", None),
+    ];
+    for (s, expect) in cases.iter() {
+      assert_eq!(&parse_time_slot(s), expect);
+    }
+    let [a, b, c, d] = [cases[0].1.clone().unwrap(), cases[1].1.clone().unwrap(), cases[3].1.clone().unwrap(), cases[4].1.clone().unwrap()];
+    assert!(!a.clashes(&b));
+    assert!(!c.clashes(&TimeSlot { weeks: Weeks::Explicit(vec![(9, 16)]), ..c.clone() }));
+    assert!(d.clashes(&TimeSlot { weeks: Weeks::Even, ..d.clone() }) && !d.clashes(&TimeSlot { weeks: Weeks::Explicit(vec![(5, 5)]), ..d.clone() }));
+    assert_eq!(types::TimeLocation::parse("待定"), types::TimeLocation::Raw("待定".to_owned()));
+  }
 }
@@ -0,0 +1,279 @@
+use select::{document::Document, node::Node, predicate::Name};
+use crate::{Hosts, types::*};
+
+/// The types whose content is a html string, it can be converted to plain text or markdown.
+///
+/// Links, images, lists and tables are preserved, relative urls are resolved against `Hosts::learn` of `hosts`, which is usually `LearnHelper::hosts`.
+/// Optional contents like `Homework::grade_content` can be converted by the `str` implementation,
+/// for example, `h.grade_content.as_deref().map(|x| x.to_markdown(&t.hosts))`.
+pub trait Render {
+  /// The html string to convert.
+  fn html(&self) -> &str;
+
+  /// Convert the html to plain text, a link or an image is written as its text followed by the url in parentheses.
+  fn to_plain_text(&self, hosts: &Hosts) -> String { render(self.html(), &hosts.learn, false) }
+
+  /// Convert the html to markdown.
+  fn to_markdown(&self, hosts: &Hosts) -> String { render(self.html(), &hosts.learn, true) }
+}
+
+impl Render for str {
+  fn html(&self) -> &str { self }
+}
+
+/// It is `Notification::content`.
+impl Render for Notification {
+  fn html(&self) -> &str { &self.content }
+}
+
+/// It is `File::description`.
+impl Render for File {
+  fn html(&self) -> &str { &self.description }
+}
+
+/// It is `HomeworkDetail::description`, which `Homework` also has.
+impl Render for HomeworkDetail {
+  fn html(&self) -> &str { &self.description }
+}
+
+/// It is `DiscussionReply0::content`.
+impl<R> Render for DiscussionReply0<R> {
+  fn html(&self) -> &str { &self.content }
+}
+
+/// It is `QuestionDetail::content`.
+impl Render for QuestionDetail {
+  fn html(&self) -> &str { &self.content }
+}
+
+// the url of a link or an image, `None` if it doesn't lead anywhere, `base` is `Hosts::learn`
+pub(crate) fn absolute_url(url: &str, base: &str) -> Option<String> {
+  let url = url.trim();
+  let lower = url.to_ascii_lowercase();
+  if url.is_empty() || url.starts_with('#') || lower.starts_with("javascript:") { return None; }
+  Some(if lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("mailto:") || lower.starts_with("data:") {
+    url.to_owned()
+  } else if url.starts_with("//") {
+    format!("https:{}", url)
+  } else if url.starts_with('/') {
+    format!("{}{}", base, url)
+  } else { format!("{}/{}", base, url) })
+}
+
+fn render(html: &str, base: &str, markdown: bool) -> String {
+  let d = Document::from(html);
+  let mut w = Writer { markdown, base, out: String::new(), indent: String::new(), marker: None, breaks: 0, space: false };
+  if let Some(body) = d.find(Name("body")).next() { w.children(body); }
+  w.out.truncate(w.out.trim_end().len());
+  w.out
+}
+
+// the separators are written lazily, so that empty paragraphs or trailing line breaks don't leave blank lines
+struct Writer<'a> {
+  markdown: bool,
+  base: &'a str,
+  out: String,
+  // the prefix of every line, it is the indentation of list items and "> " of quotes in markdown
+  indent: String,
+  // the marker of a list item, which replaces the indentation of its first line
+  marker: Option<String>,
+  // the number of line breaks before the next text, at most 2 (i.e., a blank line)
+  breaks: usize,
+  space: bool,
+}
+
+impl Writer<'_> {
+  fn push(&mut self, s: &str) {
+    if self.out.is_empty() || self.breaks > 0 || self.marker.is_some() {
+      if !self.out.is_empty() {
+        self.out.truncate(self.out.trim_end_matches(' ').len());
+        for i in 0..self.breaks {
+          if i > 0 { self.out += self.indent.trim_end(); }
+          self.out.push('\n');
+        }
+      }
+      match self.marker.take() { Some(m) => self.out += &m, None => self.out += &self.indent }
+    } else if self.space && !self.out.ends_with(char::is_whitespace) { self.out.push(' '); }
+    self.out += s;
+    self.breaks = 0;
+    self.space = false;
+  }
+
+  fn text(&mut self, s: &str) {
+    if s.starts_with(char::is_whitespace) { self.space = true; }
+    for word in s.split_whitespace() {
+      if self.markdown {
+        let escaped = word.chars().fold(String::new(), |mut acc, c| {
+          if "\\`*_[]<>|".contains(c) { acc.push('\\'); }
+          acc.push(c);
+          acc
+        });
+        self.push(&escaped);
+      } else { self.push(word); }
+      self.space = true;
+    }
+    if !s.ends_with(char::is_whitespace) { self.space = false; }
+  }
+
+  fn block(&mut self) { self.breaks = 2; }
+
+  fn line(&mut self) { self.breaks = self.breaks.max(1); }
+
+  fn children(&mut self, n: Node) {
+    for ch in n.children() { self.node(ch); }
+  }
+
+  // write `n` with `indent` appended to the current indentation
+  fn indented(&mut self, n: Node, indent: &str) {
+    let old = self.indent.clone();
+    self.indent += indent;
+    self.children(n);
+    self.indent = old;
+  }
+
+  fn node(&mut self, n: Node) {
+    if let Some(s) = n.as_text() { return self.text(s); }
+    let name = match n.name() { Some(x) => x, None => return };
+    match name {
+      "script" | "style" | "head" | "title" => {}
+      "br" => self.breaks = (self.breaks + 1).min(2),
+      "hr" => {
+        self.block();
+        self.push("---");
+        self.block();
+      }
+      "p" | "div" | "section" | "article" | "header" | "footer" | "center" | "dl" | "dd" | "dt" => {
+        self.block();
+        self.children(n);
+        self.block();
+      }
+      "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+        self.block();
+        if self.markdown { self.push(&format!("{} ", "#".repeat((name.as_bytes()[1] - b'0') as usize))); }
+        self.children(n);
+        self.block();
+      }
+      "blockquote" => {
+        self.block();
+        self.indented(n, if self.markdown { "> " } else { "  " });
+        self.block();
+      }
+      "ul" | "ol" => {
+        // a nested list follows its parent item directly
+        if self.indent.is_empty() { self.block(); } else { self.line(); }
+        let mut idx = 0;
+        for item in n.children().filter(|x| x.name() == Some("li")) {
+          idx += 1;
+          let marker = if name == "ol" { format!("{}. ", idx) } else { "- ".to_owned() };
+          self.line();
+          self.marker = Some(format!("{}{}", self.indent, marker));
+          self.indented(item, &" ".repeat(marker.len()));
+          self.marker = None;
+        }
+        if self.indent.is_empty() { self.block(); } else { self.line(); }
+      }
+      "li" => {
+        self.line();
+        self.children(n);
+        self.line();
+      }
+      "pre" => {
+        self.block();
+        if self.markdown { self.push("```"); }
+        for l in n.text().trim_matches('\n').lines() {
+          self.line();
+          self.push(l);
+        }
+        if self.markdown {
+          self.line();
+          self.push("```");
+        }
+        self.block();
+      }
+      "code" | "kbd" | "samp" if self.markdown => {
+        let s = n.text();
+        let fence = if s.contains('`') { "``" } else { "`" };
+        self.push(&format!("{}{}{}", fence, s.trim(), fence));
+      }
+      "strong" | "b" | "em" | "i" if self.markdown && !n.text().trim().is_empty() => {
+        let mark = if name == "strong" || name == "b" { "**" } else { "*" };
+        self.push(mark);
+        self.children(n);
+        self.out += mark;
+      }
+      "a" => self.link(n),
+      "img" => {
+        let alt = n.attr("alt").unwrap_or("").trim();
+        match n.attr("src").and_then(|x| absolute_url(x, self.base)) {
+          Some(src) if self.markdown => self.push(&format!("![{}]({})", alt, src)),
+          Some(src) if alt.is_empty() => self.push(&src),
+          Some(src) => self.push(&format!("{} ({})", alt, src)),
+          None => self.text(alt),
+        }
+      }
+      "table" => self.table(n),
+      _ => self.children(n),
+    }
+  }
+
+  fn link(&mut self, n: Node) {
+    let url = match n.attr("href").and_then(|x| absolute_url(x, self.base)) { Some(x) => x, None => return self.children(n) };
+    let text = n.text();
+    let text = text.trim();
+    if text == url || (text.is_empty() && n.find(Name("img")).next().is_none()) {
+      self.push(&if self.markdown { format!("<{}>", url) } else { url });
+    } else if self.markdown {
+      self.push("[");
+      self.children(n);
+      self.out += &format!("]({})", url);
+    } else {
+      self.children(n);
+      self.out += &format!(" ({})", url);
+    }
+  }
+
+  // every cell is written in one line, the first row is the header in markdown
+  fn table(&mut self, n: Node) {
+    let rows = n.find(Name("tr")).map(|r| r.children().filter(|c| c.name() == Some("td") || c.name() == Some("th")).map(|c| {
+      let mut w = Writer { markdown: self.markdown, base: self.base, out: String::new(), indent: String::new(), marker: None, breaks: 0, space: false };
+      w.children(c);
+      w.out.split_whitespace().collect::<Vec<_>>().join(" ")
+    }).collect::<Vec<_>>()).filter(|r| !r.is_empty()).collect::<Vec<_>>();
+    let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    self.block();
+    for (idx, r) in rows.iter().enumerate() {
+      let cells = (0..width).map(|i| r.get(i).map_or("", |x| x.as_str())).collect::<Vec<_>>();
+      self.line();
+      if self.markdown {
+        self.push(&format!("| {} |", cells.join(" | ")));
+        if idx == 0 {
+          self.line();
+          self.push(&format!("|{}", " --- |".repeat(width)));
+        }
+      } else { self.push(&cells.join(" | ")); }
+    }
+    self.block();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn render() {
+    let html = r#"<p>请&nbsp;查看<a href="/b/wlxt/kj/f1">课件</a>，截止 <b>3月15日</b>。</p><p> </p>
+      <ul><li>第一题<ol><li>a_b</li><li><img src="//img.example.com/1.png" alt="图"></li></ol></li><li><a href="https://example.com">https://example.com</a></li></ul>
+      <table><tr><th>题目</th><th>分数</th></tr><tr><td>PA1</td><td>10</td></tr></table><br><br><br>第二行"#;
+    let hosts = Hosts::default();
+    assert_eq!(html.to_plain_text(&hosts), "请 查看课件 (https://learn.tsinghua.edu.cn/b/wlxt/kj/f1)，截止 3月15日。\n\n\
+      - 第一题\n  1. a_b\n  2. 图 (https://img.example.com/1.png)\n- https://example.com\n\n题目 | 分数\nPA1 | 10\n\n第二行");
+    assert_eq!(html.to_markdown(&hosts), "请 查看[课件](https://learn.tsinghua.edu.cn/b/wlxt/kj/f1)，截止 **3月15日**。\n\n\
+      - 第一题\n  1. a\\_b\n  2. ![图](https://img.example.com/1.png)\n- <https://example.com>\n\n| 题目 | 分数 |\n| --- | --- |\n| PA1 | 10 |\n\n第二行");
+    assert_eq!("<blockquote><p>引用</p><p>第二段</p></blockquote><pre>  fn main() {}\n</pre>".to_markdown(&hosts), "> 引用\n>\n> 第二段\n\n```\n  fn main() {}\n```");
+    assert_eq!(absolute_url("javascript:void(0)", &hosts.learn), None);
+    assert_eq!(absolute_url("a.pdf", &hosts.learn).as_deref(), Some("https://learn.tsinghua.edu.cn/a.pdf"));
+    let hosts = Hosts { learn: "http://127.0.0.1:8080".to_owned(), ..Hosts::default() };
+    assert_eq!(r#"<a href="/b/wlxt/kj/f1">课件</a>"#.to_plain_text(&hosts), "课件 (http://127.0.0.1:8080/b/wlxt/kj/f1)");
+  }
+}
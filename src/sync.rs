@@ -0,0 +1,337 @@
+use chrono::NaiveDateTime;
+use serde::{Serialize, Deserialize};
+use std::{collections::{BTreeMap, HashMap, HashSet}, fs, io, path::{Path, PathBuf}};
+use crate::{parse::*, urls::*, types::*};
+
+/// The name of the manifest file, which is placed in the root directory of a mirror.
+pub const MANIFEST: &str = ".manifest.json";
+
+/// The kind of an item in a mirror.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemKind {
+  /// A `File`, it is stored as `<course name>/files/<category title>/<file title>.<file type>`,
+  /// or `<course name>/files/<file title>.<file type>` if it is not in any category (`File::category_id`).
+  File,
+  /// A `Homework`, it is stored as `<course name>/homework/<homework title>/homework.html`, along with its attachments.
+  Homework,
+  /// A `Notification`, it is stored as `<course name>/notifications/<notification title>/notification.html`, along with its attachment.
+  Notification,
+}
+
+/// The record of an item in the manifest. An item is downloaded again when its record changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+  /// The kind of the item.
+  pub kind: ItemKind,
+  /// `File::upload_time` for a file, `Notification::publish_time` for a notification,
+  /// and the latest one of the assign time, submit time and grade time for a homework.
+  pub time: NaiveDateTime,
+  /// `File::raw_size` for a file, the size of the generated html page for a homework or a notification.
+  pub size: u64,
+  /// A hash of the generated html page for a homework or a notification, so that every change of the page is picked up, 0 for a file.
+  #[serde(default)] pub hash: u64,
+  /// The paths of the local files of the item, relative to the root directory. The first one is the main file.
+  pub paths: Vec<PathBuf>,
+}
+
+/// The manifest of a mirror, which records the items that have been downloaded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+  /// `id => entry`, where `id` is `File::id`, `Homework::id` or `Notification::id`.
+  pub entries: BTreeMap<String, Entry>,
+}
+
+impl Manifest {
+  /// Read the manifest in `root`, return an empty manifest if it doesn't exist.
+  pub fn load(root: &Path) -> Result<Self> {
+    let path = root.join(MANIFEST);
+    match fs::read_to_string(&path) {
+      Ok(text) => parse_json(path.display().to_string(), &text),
+      Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+      Err(e) => Err(e.into()),
+    }
+  }
+
+  /// Write the manifest to `root`.
+  pub fn save(&self, root: &Path) -> Result<()> {
+    fs::create_dir_all(root)?;
+    Ok(fs::write(root.join(MANIFEST), serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?)?)
+  }
+}
+
+/// An item that is added, updated or removed in a sync.
+#[derive(Debug, Clone)]
+pub struct SyncItem {
+  /// The kind of the item.
+  pub kind: ItemKind,
+  /// `File::id`, `Homework::id` or `Notification::id`.
+  pub id: String,
+  /// The path of the main file of the item, relative to the root directory.
+  pub path: PathBuf,
+}
+
+/// The result of a sync, returned by `LearnHelper::sync_semester`.
+#[derive(Debug, Clone, Default)]
+pub struct SyncSummary {
+  /// The items that are new in web-learning.
+  pub added: Vec<SyncItem>,
+  /// The items that have changed in web-learning (or have been deleted locally), they are downloaded again.
+  pub updated: Vec<SyncItem>,
+  /// The items that no longer exist in web-learning, their local files are deleted.
+  pub removed: Vec<SyncItem>,
+}
+
+// everything of a course that a sync needs
+pub(crate) struct CourseContent {
+  pub course: Course,
+  pub file_categories: Vec<FileCategory>,
+  pub files: Vec<File>,
+  pub homework: Vec<Homework>,
+  pub notifications: Vec<Notification>,
+}
+
+pub(crate) struct Download {
+  pub url: String,
+  // the size that the downloaded file should have, if it is known in advance
+  pub size: Option<u64>,
+  pub path: PathBuf,
+  // whether a leftover part file is of an older version of the item, so it must not be resumed
+  pub restart: bool,
+}
+
+// what a sync should do, all the paths are relative to the root directory
+// the async api and the blocking api only differ in how they perform `downloads`
+#[derive(Default)]
+pub(crate) struct Plan {
+  pub removes: Vec<PathBuf>,
+  pub writes: Vec<(PathBuf, String)>,
+  pub downloads: Vec<Download>,
+  pub manifest: Manifest,
+  pub summary: SyncSummary,
+}
+
+struct Item {
+  id: String,
+  entry: Entry,
+  writes: Vec<(PathBuf, String)>,
+  downloads: Vec<Download>,
+}
+
+impl Item {
+  fn page(id: &str, kind: ItemKind, time: NaiveDateTime, path: PathBuf, page: String) -> Self {
+    let entry = Entry { kind, time, size: page.len() as u64, hash: hash(&page), paths: vec![path.clone()] };
+    Item { id: id.to_owned(), entry, writes: vec![(path, page)], downloads: Vec::new() }
+  }
+
+  fn attachment(&mut self, names: &mut Names, dir: &Path, a: &Attachment, label: &str) {
+    let path = names.claim(dir, &a.name, None, label);
+    self.entry.paths.push(path.clone());
+    // web-learning doesn't tell the size of an attachment in advance
+    self.downloads.push(Download { url: a.url.clone(), size: None, path, restart: false });
+  }
+}
+
+// the paths that have been taken in this sync
+#[derive(Default)]
+struct Names(HashSet<PathBuf>);
+
+impl Names {
+  // the path in `dir` named after `title`, with `suffix` appended if the name is taken by a previous item
+  fn claim(&mut self, dir: &Path, title: &str, ext: Option<&str>, suffix: &str) -> PathBuf {
+    let name = |s: String| match ext {
+      Some(ext) if !ext.is_empty() && !s.ends_with(&format!(".{}", ext)) => format!("{}.{}", s, ext),
+      _ => s,
+    };
+    let mut path = dir.join(name(sanitize(title)));
+    if self.0.contains(&path) { path = dir.join(name(format!("{} ({})", sanitize(title), sanitize(suffix)))); }
+    self.0.insert(path.clone());
+    path
+  }
+}
+
+// a file name that is valid on all common platforms
+pub(crate) fn sanitize(name: &str) -> String {
+  let name = name.chars().map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c }).collect::<String>();
+  let name = name.trim().trim_end_matches('.');
+  if name.is_empty() { "_".to_owned() } else { name.to_owned() }
+}
+
+// FNV-1a, it is the same in every build, unlike `std::collections::hash_map::DefaultHasher`, so it can be saved in the manifest
+pub(crate) fn hash(s: &str) -> u64 {
+  s.bytes().fold(0xcbf29ce484222325, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+fn escape(s: &str) -> String {
+  s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// `fields` are shown as a list below the title, `sections` are html strings with a heading
+fn render(title: &str, fields: &[(&str, String)], sections: &[(&str, &str)]) -> String {
+  let mut ret = format!("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{0}</title></head>\n<body>\n<h1>{0}</h1>\n<ul>\n", escape(title));
+  for (k, v) in fields { ret += &format!("<li>{}: {}</li>\n", k, escape(v)); }
+  ret += "</ul>\n";
+  for (k, v) in sections { ret += &format!("<h2>{}</h2>\n<div>{}</div>\n", k, v); }
+  ret + "</body>\n</html>\n"
+}
+
+fn render_homework(h: &Homework) -> String {
+  let mut fields = vec![("Assigned", h.assign_time.to_string()), ("Deadline", h.deadline.to_string())];
+  if let Some(x) = h.submit_time { fields.push(("Submitted", x.to_string())); }
+  if let Some(x) = h.grade { fields.push(("Grade", x.to_string())); }
+  if let Some(x) = &h.grader_name { fields.push(("Graded by", x.clone())); }
+  if let Some(x) = h.grade_time { fields.push(("Graded", x.to_string())); }
+  let mut sections = vec![("Description", h.description.as_str())];
+  if let Some(x) = &h.submit_content { sections.push(("Submission", x)); }
+  if let Some(x) = &h.grade_content { sections.push(("Comment", x)); }
+  render(&h.title, &fields, &sections)
+}
+
+fn render_notification(n: &Notification) -> String {
+  let fields = [("Publisher", n.publisher.clone()), ("Published", n.publish_time.to_string())];
+  render(&n.title, &fields, &[("Content", &n.content)])
+}
+
+// `learn` is `Hosts::learn`, `old` is the manifest of the last sync
+pub(crate) fn plan(root: &Path, learn: &str, old: &Manifest, contents: &[CourseContent]) -> Plan {
+  let (mut names, mut items) = (Names::default(), Vec::new());
+  for c in contents {
+    let dir = names.claim(Path::new(""), &c.course.name, None, &c.course.id);
+    let files_dir = dir.join("files");
+    let category_dirs = c.file_categories.iter().map(|x| (&x.id, names.claim(&files_dir, &x.title, None, &x.id))).collect::<HashMap<_, _>>();
+    for f in &c.files {
+      let dir = f.category_id.as_ref().and_then(|x| category_dirs.get(x)).unwrap_or(&files_dir);
+      let path = names.claim(dir, &f.title, Some(&f.file_type), &f.id);
+      let entry = Entry { kind: ItemKind::File, time: f.upload_time, size: f.raw_size as u64, hash: 0, paths: vec![path.clone()] };
+      let download = Download { url: learn.to_owned() + &FILE_DOWNLOAD(&f.id), size: Some(f.raw_size as u64), path, restart: false };
+      items.push(Item { id: f.id.to_string(), entry, writes: Vec::new(), downloads: vec![download] });
+    }
+    for h in &c.homework {
+      let dir = names.claim(&dir.join("homework"), &h.title, None, &h.id);
+      let time = [Some(h.assign_time), h.submit_time, h.grade_time].iter().filter_map(|x| *x).max().unwrap();
+      // the page is claimed before the attachments, so that an attachment with the same name doesn't overwrite it
+      let page = names.claim(&dir, "homework.html", None, "page");
+      let mut item = Item::page(&h.id, ItemKind::Homework, time, page, render_homework(h));
+      if let Some(a) = &h.attachment { item.attachment(&mut names, &dir, a, "attachment"); }
+      if let Some(a) = &h.submit_attachment { item.attachment(&mut names, &dir, a, "submission"); }
+      if let Some(a) = &h.grade_attachment { item.attachment(&mut names, &dir, a, "grade"); }
+      items.push(item);
+    }
+    for n in &c.notifications {
+      let dir = names.claim(&dir.join("notifications"), &n.title, None, &n.id);
+      let page = names.claim(&dir, "notification.html", None, "page");
+      let mut item = Item::page(&n.id, ItemKind::Notification, n.publish_time, page, render_notification(n));
+      if let Some(a) = &n.attachment { item.attachment(&mut names, &dir, a, "attachment"); }
+      items.push(item);
+    }
+  }
+  let mut plan = Plan::default();
+  for item in items {
+    let sync_item = SyncItem { kind: item.entry.kind, id: item.id.clone(), path: item.entry.paths[0].clone() };
+    let (changed, restart) = match old.entries.get(&item.id) {
+      // the local files may have been deleted by hand
+      Some(e) if *e == item.entry && e.paths.iter().all(|p| root.join(p).exists()) => (false, false),
+      // the downloads of an unchanged item can resume, while those of a changed one start over
+      Some(e) => { plan.summary.updated.push(sync_item); (true, *e != item.entry) }
+      None => { plan.summary.added.push(sync_item); (true, false) }
+    };
+    if changed {
+      plan.writes.extend(item.writes);
+      plan.downloads.extend(item.downloads.into_iter().map(|d| Download { restart, ..d }));
+    }
+    plan.manifest.entries.insert(item.id, item.entry);
+  }
+  for (id, e) in &old.entries {
+    if !plan.manifest.entries.contains_key(id) {
+      plan.summary.removed.push(SyncItem { kind: e.kind, id: id.clone(), path: e.paths[0].clone() });
+    }
+  }
+  // the old files that are not used by any item now
+  let used = plan.manifest.entries.values().flat_map(|e| e.paths.iter()).collect::<HashSet<_>>();
+  plan.removes = old.entries.values().flat_map(|e| e.paths.iter()).filter(|p| !used.contains(p)).cloned().collect();
+  plan
+}
+
+pub(crate) fn write(root: &Path, path: &Path, content: &str) -> io::Result<()> {
+  let path = root.join(path);
+  fs::create_dir_all(path.parent().unwrap())?;
+  fs::write(path, content)
+}
+
+// also remove the directories that become empty
+pub(crate) fn remove(root: &Path, path: &Path) -> io::Result<()> {
+  match fs::remove_file(root.join(path)) {
+    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+    _ => {}
+  }
+  for dir in path.ancestors().skip(1).filter(|x| !x.as_os_str().is_empty()) {
+    if fs::remove_dir(root.join(dir)).is_err() { break; } // not empty
+  }
+  Ok(())
+}
+
+// a download is written to "<path>.part" first, so that it can resume if interrupted
+pub(crate) fn part_path(root: &Path, path: &Path) -> PathBuf {
+  let mut ret = root.join(path).into_os_string();
+  ret.push(".part");
+  ret.into()
+}
+
+// open the part file in append mode, and return it with its current length, it is emptied first if `restart`
+pub(crate) fn open_part(root: &Path, path: &Path, restart: bool) -> io::Result<(fs::File, u64)> {
+  let part = part_path(root, path);
+  fs::create_dir_all(part.parent().unwrap())?;
+  let file = fs::OpenOptions::new().create(true).append(true).open(part)?;
+  if restart { file.set_len(0)?; }
+  let len = file.metadata()?.len();
+  Ok((file, len))
+}
+
+// move the part file to `path` if the download succeeded, or remove it if the content is wrong, so that the next sync starts over
+pub(crate) fn finish_part(root: &Path, path: &Path, res: &Result<Downloaded>) -> io::Result<()> {
+  match res {
+    Ok(_) => fs::rename(part_path(root, path), root.join(path)),
+    Err(Error::SizeMismatch { .. }) => fs::remove_file(part_path(root, path)),
+    Err(_) => Ok(()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn content(homework: Vec<Homework>) -> CourseContent {
+    let course = serde_json::from_str(r#"{"wlkcid":"c1","kcm":"编译原理","ywkcm":"","jsm":"","jsh":"","kch":"","kxh":0}"#).unwrap();
+    CourseContent { course, file_categories: Vec::new(), files: Vec::new(), homework, notifications: Vec::new() }
+  }
+
+  fn homework() -> Homework {
+    serde_json::from_str(r#"{"wlkcid":"c1","zyid":"h1","xszyid":"s1","bt":"PA1","kssjStr":"2020-03-01 08:00","jzsjStr":"2020-03-15 23:59",
+      "scsjStr":null,"zynrStr":null,"cj":null,"pysjStr":null,"jsm":null,"pynr":null}"#).unwrap()
+  }
+
+  #[test]
+  fn page_name() {
+    let mut h = homework();
+    h.submit_attachment = Some(Attachment { name: "homework.html".to_owned(), url: "https://learn.tsinghua.edu.cn/a".to_owned() });
+    let plan = plan(Path::new("/nonexistent"), "https://learn.tsinghua.edu.cn", &Manifest::default(), &[content(vec![h])]);
+    let paths = &plan.manifest.entries["h1"].paths;
+    assert_eq!(paths[0], Path::new("编译原理/homework/PA1/homework.html"));
+    assert_eq!(paths[1], Path::new("编译原理/homework/PA1/homework.html (submission)"));
+  }
+
+  #[test]
+  fn page_change() {
+    let entry = |h: Homework| plan(Path::new("/nonexistent"), "", &Manifest::default(), &[content(vec![h])]).manifest.entries.remove("h1").unwrap();
+    let mut h = homework();
+    h.grade_content = Some("不错".to_owned());
+    let old = entry(h.clone());
+    // neither of them changes the time or the size of the page
+    h.grade_content = Some("很好".to_owned());
+    assert_ne!(entry(h.clone()), old);
+    h.grade_content = Some("不错".to_owned());
+    h.deadline += chrono::Duration::days(1);
+    assert_ne!(entry(h.clone()), old);
+    h.deadline -= chrono::Duration::days(1);
+    assert_eq!(entry(h), old);
+  }
+}
@@ -0,0 +1,434 @@
+use std::{collections::HashMap, io::{self, BufRead, BufReader, Read, Write}, net::{SocketAddr, TcpListener, TcpStream}, thread, time::Duration};
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use crate::{Hosts, DELETE_DR_TIMEOUT, PAGE_SIZE, types::beijing_now, urls::*};
+
+/// The only username that `TestServer` accepts.
+pub const USERNAME: &str = "2017011000";
+/// The only password that `TestServer` accepts.
+pub const PASSWORD: &str = "password";
+/// The only semester that has courses in `TestServer`, the other semesters in `semester_id_list` have no course.
+pub const SEMESTER: &str = "2019-2020-2";
+/// The only course that has notifications, files, homework and discussions in `TestServer`,
+/// the other courses in `course_list` have nothing.
+pub const COURSE: &str = "c1";
+/// A course that is not in `course_list`, with `BUSY_COURSE_ITEMS` files and discussions, which take several pages to list.
+pub const BUSY_COURSE: &str = "busy";
+/// The number of files and discussions in `BUSY_COURSE`.
+pub const BUSY_COURSE_ITEMS: usize = 2 * PAGE_SIZE + 50;
+
+/// The content of a file or an attachment that `TestServer` serves, `id` is `File::id`, or the last part of `Attachment::url`.
+///
+/// Files support "Range" requests, while attachments ignore "Range" and always respond with the whole content.
+pub fn file_content(id: &str) -> Option<Vec<u8>> {
+  let size = match id { "f1" => 1048576, "f2" => 20480, "a1" | "a2" | "a3" | "a4" | "a5" | "a6" | "upload" => 1000, _ => return None };
+  Some((0..size).map(|i| (i % 251) as u8).collect())
+}
+
+/// A request received by `TestServer`, recorded for later inspection.
+#[derive(Debug, Clone)]
+pub struct Request {
+  /// "GET" or "POST".
+  pub method: String,
+  /// The path and query of the request, for example, "/b/wlxt/kj/wlkc_kjxxb/student/kjxxbByWlkcidAndSizeForStudent?wlkcid=c1&size=200&currentPage=1".
+  pub path: String,
+  /// The raw request body. For a multipart form, you can simply check whether it contains the expected text.
+  pub body: Vec<u8>,
+}
+
+/// A fake web-learning server listening on a random local port, serving the canned data in the `fixtures` directory.
+///
+/// It plays both the role of `Hosts::id` and `Hosts::learn`, and it understands all the requests that `LearnHelper` sends,
+/// so the whole api can be exercised without a real student account.
+/// Submitting a homework changes the attachment of the submission in its detail page, except for the graded homework, whose submission is ignored.
+/// Deleting the reply "r3" removes it from the discussion replies page.
+/// The server stops when it is dropped.
+pub struct TestServer {
+  addr: SocketAddr,
+  state: Arc<Mutex<State>>,
+  stopped: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct State {
+  sessions: Vec<String>,
+  session_count: usize,
+  requests: Vec<Request>,
+  failures: Vec<String>,
+  // `(prefix, location)` of `redirect_requests`
+  redirects: Vec<(String, String)>,
+  // the prefix of `expire_sessions_after`
+  expire_after: Option<String>,
+  in_flight: usize,
+  max_in_flight: usize,
+  // `student_homework_id => the name of the submitted attachment`, it overrides the one in the homework detail page
+  submissions: HashMap<String, Option<String>>,
+  // `student_homework_id => (the content, the time) of the submission`, they override the ones in the homework lists
+  submitted: HashMap<String, (String, String)>,
+  // the `ReplyId`s removed from the discussion replies page
+  deleted_replies: Vec<String>,
+}
+
+impl TestServer {
+  /// Start the server in a background thread.
+  pub fn start() -> Self {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind test server");
+    let addr = listener.local_addr().expect("failed to bind test server");
+    let (state, stopped) = (Arc::new(Mutex::new(State::default())), Arc::new(AtomicBool::new(false)));
+    let (state1, stopped1) = (state.clone(), stopped.clone());
+    thread::spawn(move || for stream in listener.incoming() {
+      if stopped1.load(Ordering::SeqCst) { break; }
+      if let Ok(stream) = stream {
+        let state = state1.clone();
+        thread::spawn(move || { let _ = serve(stream, addr, &state); });
+      }
+    });
+    Self { addr, state, stopped }
+  }
+
+  /// The `Hosts` to pass to `LearnHelper::login_to`.
+  pub fn hosts(&self) -> Hosts {
+    let host = format!("http://{}", self.addr);
+    Hosts { id: host.clone(), learn: host }
+  }
+
+  /// All the requests received so far, in the order they arrived.
+  pub fn requests(&self) -> Vec<Request> { self.state.lock().unwrap().requests.clone() }
+
+  /// Invalidate all the login sessions, as if they have been idle for too long.
+  pub fn expire_sessions(&self) { self.state.lock().unwrap().sessions.clear(); }
+
+  /// Invalidate all the login sessions once, right after responding to the next request whose path and query start with `prefix`.
+  pub fn expire_sessions_after(&self, prefix: impl Into<String>) { self.state.lock().unwrap().expire_after = Some(prefix.into()); }
+
+  /// Respond with "500 Internal Server Error" to every later request whose path and query start with `prefix`.
+  pub fn fail_requests(&self, prefix: impl Into<String>) { self.state.lock().unwrap().failures.push(prefix.into()); }
+
+  /// Redirect every later request whose path and query start with `prefix` to `location` on the server (with "302 Found").
+  pub fn redirect_requests(&self, prefix: impl Into<String>, location: &str) {
+    let location = format!("http://{}{}", self.addr, location);
+    self.state.lock().unwrap().redirects.push((prefix.into(), location));
+  }
+
+  /// The maximum number of requests that have been processed at the same time so far.
+  pub fn max_in_flight(&self) -> usize { self.state.lock().unwrap().max_in_flight }
+}
+
+impl Drop for TestServer {
+  fn drop(&mut self) {
+    self.stopped.store(true, Ordering::SeqCst);
+    let _ = TcpStream::connect(self.addr); // wake up the blocking `accept`
+  }
+}
+
+#[derive(Default)]
+struct Response {
+  status: u16,
+  body: Vec<u8>,
+  headers: Vec<(&'static str, String)>,
+  // how long to wait before responding, without blocking the other requests
+  delay: Duration,
+}
+
+fn json(body: impl Into<String>) -> Response {
+  Response { status: 200, body: body.into().into_bytes(), headers: vec![("Content-Type", "application/json;charset=UTF-8".to_owned())], ..Default::default() }
+}
+
+fn html(body: impl Into<String>) -> Response {
+  Response { status: 200, body: body.into().into_bytes(), headers: vec![("Content-Type", "text/html;charset=UTF-8".to_owned())], ..Default::default() }
+}
+
+fn redirect(location: String) -> Response { Response { status: 302, headers: vec![("Location", location)], ..Default::default() } }
+
+// `range` is the start of the "Range" header, it is ignored if `None` is passed as `file_name`
+fn download(id: &str, file_name: Option<&str>, range: Option<usize>) -> Response {
+  let content = match file_content(id) { Some(x) => x, None => return not_found() };
+  let disposition = ("Content-Disposition", match file_name {
+    Some(name) => format!("attachment; filename=\"{}\"", name),
+    None => format!("attachment; filename*=UTF-8''{}.bin", id),
+  });
+  match range {
+    Some(start) if file_name.is_some() && start >= content.len() =>
+      Response { status: 416, headers: vec![("Content-Range", format!("bytes */{}", content.len()))], ..Default::default() },
+    Some(start) if file_name.is_some() => {
+      let range = ("Content-Range", format!("bytes {}-{}/{}", start, content.len() - 1, content.len()));
+      Response { status: 206, body: content[start..].to_vec(), headers: vec![disposition, range], ..Default::default() }
+    }
+    _ => Response { status: 200, body: content, headers: vec![disposition], ..Default::default() },
+  }
+}
+
+fn not_found() -> Response { Response { status: 404, ..html("not found") } }
+
+// the page that web-learning redirects to after login
+const COURSE_INDEX: &str = "/f/wlxt/index/course/student/";
+
+// the items of `BUSY_COURSE` in the page, joined by ','
+fn busy_page(page: usize, size: usize, item: impl Fn(usize) -> String) -> String {
+  let start = (page.max(1) - 1) * size;
+  (start..BUSY_COURSE_ITEMS.min(start + size)).map(item).collect::<Vec<_>>().join(",")
+}
+
+// the value of a text field in a multipart form, or the file name of a file field
+fn multipart_field(body: &str, name: &str) -> Option<String> {
+  let rest = &body[body.find(&format!("name=\"{}\"", name))? + name.len() + 7..];
+  if let Some(rest) = rest.strip_prefix("; filename=\"") { return rest.split('"').next().map(str::to_owned); }
+  let rest = &rest[rest.find("\r\n\r\n")? + 4..];
+  Some(rest[..rest.find("\r\n")?].to_owned())
+}
+
+// the discussion replies `page` without the sub-replies in `deleted`
+fn without_replies(page: &str, deleted: &[String]) -> String {
+  let mut page = page.to_owned();
+  for id in deleted {
+    if let Some(start) = page.find(&format!("<div class=\"item\" id=\"item_{}\">", id)) {
+      let end = start + page[start..].find("</div>").unwrap() + "</div>".len();
+      page.replace_range(start..end, "");
+    }
+  }
+  page
+}
+
+// `page` with the attachment of the submission replaced by `name`, or removed if `None`
+// a canned homework list with the submissions applied, the homework stays in the same list for simplicity
+fn homework_list(list: &str, state: &State) -> Response {
+  let mut list = serde_json::from_str::<serde_json::Value>(list).unwrap();
+  for x in list["object"]["aaData"].as_array_mut().unwrap() {
+    if let Some((content, time)) = state.submitted.get(x["xszyid"].as_str().unwrap_or("")) {
+      x["zynrStr"] = content.clone().into();
+      x["scsjStr"] = time.clone().into();
+    }
+  }
+  json(list.to_string())
+}
+
+fn with_submit_attachment(page: &str, name: Option<&str>) -> String {
+  const DIV: &str = "<div class=\"fl right\">";
+  let start = page.find("提交附件").unwrap();
+  let start = start + page[start..].find(DIV).unwrap() + DIV.len();
+  let end = start + page[start..].find("</div>").unwrap();
+  let a = name.map_or(String::new(), |name| format!(concat!("\n<span class=\"ftitle\">\n<a href=\"/f/wlxt/kj/wlkc_kjxxb/student/beforePlayJson?wjid=upload",
+    "&amp;downloadUrl=/b/wlxt/kczy/zy/student/downloadFile/c1/upload\">{}</a>\n</span>\n"), name));
+  format!("{}{}{}", &page[..start], a, &page[end..])
+}
+
+// the path part of a url in `urls.rs`
+fn path_of(url: &str) -> &str { url.split('?').next().unwrap() }
+
+fn serve(stream: TcpStream, addr: SocketAddr, state: &Mutex<State>) -> io::Result<()> {
+  let mut reader = BufReader::new(stream.try_clone()?);
+  let mut line = String::new();
+  reader.read_line(&mut line)?;
+  let mut parts = line.split_whitespace();
+  let (method, path) = (parts.next().unwrap_or("").to_owned(), parts.next().unwrap_or("").to_owned());
+  let (mut content_length, mut chunked, mut cookie, mut range) = (0, false, String::new(), None);
+  loop {
+    line.clear();
+    if reader.read_line(&mut line)? == 0 || line.trim().is_empty() { break; }
+    let (name, value) = match line.find(':') { Some(i) => (line[..i].trim().to_ascii_lowercase(), line[i + 1..].trim()), None => continue };
+    match name.as_str() {
+      "content-length" => content_length = value.parse().unwrap_or(0),
+      "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+      "cookie" => cookie = value.to_owned(),
+      "range" => range = value.trim_start_matches("bytes=").trim_end_matches('-').parse().ok(),
+      _ => {}
+    }
+  }
+  let mut body = Vec::new();
+  if chunked {
+    loop {
+      line.clear();
+      reader.read_line(&mut line)?;
+      let len = usize::from_str_radix(line.trim(), 16).unwrap_or(0);
+      let mut chunk = vec![0; len + 2]; // with the trailing "\r\n"
+      reader.read_exact(&mut chunk)?;
+      if len == 0 { break; }
+      body.extend_from_slice(&chunk[..len]);
+    }
+  } else {
+    body.resize(content_length, 0);
+    reader.read_exact(&mut body)?;
+  }
+  let session = cookie.split(';').filter_map(|x| {
+    let mut kv = x.trim().splitn(2, '=');
+    if kv.next()? == "JSESSIONID" { kv.next() } else { None }
+  }).next().map(str::to_owned);
+  let req = Request { method, path, body };
+  let res = {
+    let mut state = state.lock().unwrap();
+    state.in_flight += 1;
+    state.max_in_flight = state.max_in_flight.max(state.in_flight);
+    if state.failures.iter().any(|x| req.path.starts_with(x.as_str())) {
+      Response { status: 500, ..html("<html><body>系统错误</body></html>") }
+    } else if let Some((_, location)) = state.redirects.iter().find(|x| req.path.starts_with(x.0.as_str())) {
+      redirect(location.clone())
+    } else { route(&req, session, range, &format!("http://{}", addr), &mut state) }
+  };
+  {
+    let mut state = state.lock().unwrap();
+    if state.expire_after.as_ref().map_or(false, |x| req.path.starts_with(x.as_str())) {
+      state.expire_after = None;
+      state.sessions.clear();
+    }
+  }
+  state.lock().unwrap().requests.push(req);
+  thread::sleep(res.delay);
+  let res = respond(stream, res);
+  state.lock().unwrap().in_flight -= 1;
+  res
+}
+
+fn respond(mut stream: TcpStream, res: Response) -> io::Result<()> {
+  let reason = match res.status { 200 => "OK", 206 => "Partial Content", 302 => "Found", 416 => "Range Not Satisfiable", 500 => "Internal Server Error", _ => "Not Found" };
+  write!(stream, "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n", res.status, reason, res.body.len())?;
+  for (name, value) in res.headers { write!(stream, "{}: {}\r\n", name, value)?; }
+  write!(stream, "\r\n")?;
+  stream.write_all(&res.body)?;
+  stream.flush()
+}
+
+fn route(req: &Request, session: Option<String>, range: Option<usize>, host: &str, state: &mut State) -> Response {
+  let (path, query) = match req.path.find('?') { Some(i) => (&req.path[..i], &req.path[i + 1..]), None => (&req.path[..], "") };
+  let param = |name: &str| query.split('&').filter_map(|x| {
+    let mut kv = x.splitn(2, '=');
+    if kv.next()? == name { kv.next() } else { None }
+  }).next().unwrap_or("");
+  if path == path_of(LOGIN) {
+    let form = String::from_utf8_lossy(&req.body).into_owned();
+    let field = |name: &str| form.split('&').filter_map(|x| {
+      let mut kv = x.splitn(2, '=');
+      if kv.next()? == name { kv.next() } else { None }
+    }).next().unwrap_or("").to_owned();
+    return if field("i_user") == USERNAME && field("i_pass") == PASSWORD {
+      html(format!("<script>window.location.replace(\"{}{}\");</script>", host, AUTH_ROAM("fake-ticket")))
+    } else { html(include_str!("../fixtures/login_page.html")) };
+  }
+  if path == path_of(&AUTH_ROAM("")) {
+    if param("ticket") != "fake-ticket" { return redirect(format!("{}{}", host, LOGIN_PAGE)); }
+    state.session_count += 1;
+    let id = format!("session{}", state.session_count);
+    state.sessions.push(id.clone());
+    // the session cookie is only visible in the middle of the redirections
+    let mut res = redirect(format!("{}{}", host, COURSE_INDEX));
+    res.headers.push(("Set-Cookie", format!("JSESSIONID={}; Path=/", id)));
+    return res;
+  }
+  if path == LOGIN_PAGE { return html(include_str!("../fixtures/login_page.html")); }
+  // every request below requires a valid session, web-learning redirects to the login page if not logged in
+  let session = match session { Some(s) if state.sessions.contains(&s) => s, _ => return redirect(format!("{}{}", host, LOGIN_PAGE)) };
+  // the canned lists only have the first page
+  let (page, size) = (param("currentPage").parse().unwrap_or(1), param("size").parse().unwrap_or(PAGE_SIZE));
+  let course = match param("wlkcid") { c if c == BUSY_COURSE || page == 1 => c, _ => "" };
+  let empty_list = || json(r#"{"object":{"aaData":[]}}"#);
+  match path {
+    _ if path == COURSE_INDEX => html("<html><body></body></html>"),
+    _ if path == LOGOUT => {
+      state.sessions.retain(|s| *s != session);
+      let mut res = redirect(format!("{}{}", host, LOGIN_PAGE));
+      res.headers.push(("Set-Cookie", "JSESSIONID=; Max-Age=0; Path=/".to_owned()));
+      res
+    }
+    _ if path == SEMESTER_LIST => json(include_str!("../fixtures/semesters.json")),
+    _ if path == CURRENT_SEMESTER => json(include_str!("../fixtures/current_semester.json")),
+    _ if path.starts_with(COURSE_LIST("").as_str()) => match &path[COURSE_LIST("").len()..] {
+      s if s == SEMESTER => json(include_str!("../fixtures/courses.json")),
+      s if s.split('-').count() == 3 => json(r#"{"resultList":[]}"#),
+      _ => html("<html><body>系统错误</body></html>"), // a malformed semester id
+    },
+    _ if path == path_of(&COURSE_TIME_LOCATION("")) =>
+      if param("id") == COURSE { json(include_str!("../fixtures/time_location.json")) } else { json("[]") },
+    _ if path == path_of(&FILE_CATEGORY_LIST("")) =>
+      if course == COURSE { json(include_str!("../fixtures/file_categories.json")) } else { json(r#"{"object":{"rows":[]}}"#) },
+    _ if path == path_of(&FILE_LIST("", 0)) && course == BUSY_COURSE => json(format!(r#"{{"object":[{}]}}"#, busy_page(page, size, |i| format!(
+      r#"{{"wjid":"busy-f{0}","bt":"文件{0}","ms":"","wjdx":1024,"fileSize":"1K","scsj":"2020-02-17 08:00","isNew":0,"sfqd":0,"llcs":0,"xzcs":0,"wjlx":"txt"}}"#, i)))),
+    _ if path == path_of(&DISCUSSION_LIST("", 0)) && course == BUSY_COURSE => json(format!(r#"{{"object":{{"resultsList":[{}]}}}}"#, busy_page(page, size, |i| format!(
+      r#"{{"id":"busy-d{0}","bqid":"b1","bt":"讨论{0}","fbrxm":"张三","fbsj":"2020-03-02 10:00:00","zhhfrxm":"","zhhfsj":null,"djs":0,"hfcs":0}}"#, i)))),
+    _ if path == path_of(&NOTIFICATION_LIST("", 0)) =>
+      if course == COURSE { json(include_str!("../fixtures/notifications.json")) } else { empty_list() },
+    _ if path == path_of(&NOTIFICATION_DETAIL("", "")) => html(include_str!("../fixtures/notification_detail.html")),
+    _ if path == path_of(&FILE_LIST("", 0)) =>
+      if course == COURSE { json(include_str!("../fixtures/files.json")) } else { json(r#"{"object":[]}"#) },
+    _ if path == path_of(&HOMEWORK_LIST_NEW("", 0)) =>
+      if course == COURSE { homework_list(include_str!("../fixtures/homework_new.json"), state) } else { empty_list() },
+    _ if path == path_of(&HOMEWORK_LIST_SUBMITTED("", 0)) =>
+      if course == COURSE { homework_list(include_str!("../fixtures/homework_submitted.json"), state) } else { empty_list() },
+    _ if path == path_of(&HOMEWORK_LIST_GRADED("", 0)) =>
+      if course == COURSE { homework_list(include_str!("../fixtures/homework_graded.json"), state) } else { empty_list() },
+    _ if path == path_of(&HOMEWORK_DETAIL("", "", "")) => {
+      let page = match param("zyid") {
+        "h1" => include_str!("../fixtures/homework_detail_new.html"),
+        "h2" => include_str!("../fixtures/homework_detail_submitted.html"),
+        "h3" => include_str!("../fixtures/homework_detail_graded.html"),
+        _ => return not_found(),
+      };
+      match state.submissions.get(param("xszyid")) { Some(name) => html(with_submit_attachment(page, name.as_deref())), None => html(page) }
+    }
+    _ if path == path_of(&FILE_DOWNLOAD("")) => match param("wjid") {
+      "f1" => download("f1", Some("第一讲.pdf"), range),
+      "f2" => download("f2", Some("实验框架.zip"), range),
+      id => download(id, None, range), // a notification attachment
+    },
+    _ if path.starts_with("/b/wlxt/kczy/zy/student/downloadFile/") => download(path.rsplit('/').next().unwrap(), None, range),
+    _ if path == HOMEWORK_SUBMIT => {
+      let body = String::from_utf8_lossy(&req.body);
+      let field = |name| multipart_field(&body, name);
+      let id = field("xszyid").unwrap_or_default();
+      // the submission of the graded homework is accepted but ignored, so that a failed verification can be tested
+      if id != "s3" {
+        let time = beijing_now().format("%Y-%m-%d %H:%M").to_string();
+        state.submitted.insert(id.clone(), (field("zynr").unwrap_or_default(), time));
+        match (field("fileupload"), field("isDeleted").as_deref()) {
+          (Some(name), _) if name != "undefined" => { state.submissions.insert(id, Some(name)); }
+          (_, Some("1")) => { state.submissions.insert(id, None); }
+          _ => {}
+        }
+      }
+      json(r#"{"result":"success","msg":""}"#)
+    }
+    // only "r3" is published by the student, so only it can be edited
+    _ if path == REPLY_DISCUSSION => match multipart_field(&String::from_utf8_lossy(&req.body), "id").as_deref() {
+      None | Some("r3") => json(r#"{"result":"success","msg":""}"#),
+      _ => json(r#"{"result":"error","msg":"no permission"}"#),
+    },
+    _ if path == ASK_QUESTION => json(r#"{"result":"success","msg":""}"#),
+    _ if path == path_of(&NOTIFICATION_MARK_READ("", "")) || path == path_of(&FILE_MARK_READ("", "")) =>
+      match (course, param("ggid"), param("wjid")) {
+        (COURSE, "n1", "") | (COURSE, "n2", "") | (COURSE, "", "f1") | (COURSE, "", "f2") => json(r#"{"result":"success","msg":""}"#),
+        _ => json(r#"{"result":"error","msg":"not found"}"#),
+      },
+    _ if path == path_of(&DISCUSSION_LIST("", 0)) =>
+      if course == COURSE { json(include_str!("../fixtures/discussions.json")) } else { json(r#"{"object":{"resultsList":[]}}"#) },
+    _ if path == path_of(&DISCUSSION_REPLIES("", "", "")) =>
+      if param("id") == "d1" { html(without_replies(include_str!("../fixtures/discussion_replies.html"), &state.deleted_replies)) } else { html("<html><body></body></html>") },
+    _ if path == path_of(&DISCUSSION_BOARD_LIST("")) =>
+      if course == COURSE { json(include_str!("../fixtures/discussion_boards.json")) } else { json(r#"{"object":{"rows":[]}}"#) },
+    _ if path == SAVE_DISCUSSION => {
+      let body = String::from_utf8_lossy(&req.body);
+      // the student (王五) only published "d2"
+      match (multipart_field(&body, "bqid").as_deref(), multipart_field(&body, "id").as_deref()) {
+        (Some("b1"), None) | (Some("b2"), None) | (Some("b1"), Some("d2")) | (Some("b2"), Some("d2")) => json(r#"{"result":"success","msg":""}"#),
+        _ => json(r#"{"result":"error","msg":"no permission"}"#),
+      }
+    }
+    _ if path == path_of(&DELETE_DISCUSSION("", "")) => match param("id") {
+      "d2" => json(r#"{"result":"success","msg":""}"#),
+      _ => json(r#"{"result":"error","msg":"no permission"}"#),
+    },
+    _ if path == path_of(&QUESTION_LIST("", 0)) =>
+      if course == COURSE { json(include_str!("../fixtures/questions.json")) } else { json(r#"{"object":{"resultsList":[]}}"#) },
+    _ if path == path_of(&QUESTION_DETAIL("", "")) => match param("id") {
+      "q1" => html(include_str!("../fixtures/question_detail.html")),
+      "q2" => html(include_str!("../fixtures/question_detail_unanswered.html")),
+      _ => not_found(),
+    },
+    // like web-learning, a successful deletion responds slowly, longer than `DELETE_DR_TIMEOUT`, and so does the refused deletion of "r2"
+    _ if path == path_of(&DELETE_DISCUSSION_REPLY("", "")) => match param("id") {
+      "r3" => {
+        state.deleted_replies.push("r3".to_owned());
+        Response { delay: 2 * DELETE_DR_TIMEOUT, ..json(r#"{"result":"success","msg":""}"#) }
+      }
+      "r2" => Response { delay: 2 * DELETE_DR_TIMEOUT, ..json(r#"{"result":"error","msg":"no permission"}"#) },
+      _ => json(r#"{"result":"error","msg":"no permission"}"#),
+    },
+    _ => not_found(),
+  }
+}
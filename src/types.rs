@@ -1,19 +1,67 @@
-use chrono::NaiveDateTime;
-use serde::Deserialize;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Utc, Weekday};
+use serde::{Serialize, Deserialize};
 use derive_more::{From, Deref, DerefMut};
 use std::fmt;
-use crate::{parse::*, urls::*};
+use crate::{LearnHelper, Hosts, parse::*, urls::*};
 
 /// The errors that may occur when communicating with web-learning.
 ///
-/// There is no essential difference between the sources of these two variants,
-/// the only difference is whether `reqwest` reports the error or my program does.
+/// `Network` and `Message` are the general cases, the other variants carry enough context to
+/// diagnose what went wrong (typically web-learning changes its pages or apis) from the error message alone.
 #[derive(Debug, From)]
 pub enum Error {
   /// `reqwest` reports this error.
   Network(reqwest::Error),
   /// Subsequent handling reports this error.
   Message(&'static str),
+  /// The destination of a download reports this error.
+  Io(std::io::Error),
+  /// A html page doesn't have the expected structure.
+  #[from(ignore)]
+  Parse {
+    /// Which kind of page it is, for example, "homework detail".
+    page: &'static str,
+    /// The url of the page.
+    url: String,
+    /// The step that failed, for example, "attachment href".
+    step: &'static str,
+    /// The beginning of the html around the failed step.
+    snippet: String,
+  },
+  /// A json response (or the manifest of `sync`) doesn't have the expected structure.
+  #[from(ignore)]
+  Json {
+    /// The url of the request, or the path of the manifest.
+    endpoint: String,
+    /// The beginning of the response body, or the manifest.
+    body_excerpt: String,
+    /// The error reported by `serde_json`.
+    source: serde_json::Error,
+  },
+  /// Login failed, the username or password is probably wrong.
+  #[from(ignore)]
+  Auth,
+  /// The login session is expired, or it is not logged in at all. Web-learning redirects to the login page in this case.
+  #[from(ignore)]
+  SessionExpired,
+  /// Web-learning responded with a status code other than 2xx.
+  #[from(ignore)]
+  Server {
+    /// The status code.
+    status: u16,
+    /// The url of the request.
+    url: String,
+  },
+  /// The size of a downloaded file is different from what web-learning claims.
+  #[from(ignore)]
+  SizeMismatch {
+    /// The url of the file.
+    url: String,
+    /// The size in `File::raw_size`, or the size that the server claims in the response headers.
+    expected: u64,
+    /// The size of the file after downloading, including the part that the download resumed from.
+    actual: u64,
+  },
 }
 
 impl fmt::Display for Error {
@@ -21,212 +69,754 @@ impl fmt::Display for Error {
     match self {
       Error::Network(e) => write!(f, "network error: {}", e),
       Error::Message(m) => write!(f, "error: {}", m),
+      Error::Io(e) => write!(f, "io error: {}", e),
+      Error::Parse { page, url, step, snippet } => write!(f, "failed to parse {} page at step \"{}\" ({}): {}", page, step, url, snippet),
+      Error::Json { endpoint, body_excerpt, source } => write!(f, "invalid json response ({}): {}, body: {}", endpoint, source, body_excerpt),
+      Error::Auth => write!(f, "authentication failed, the username or password may be wrong"),
+      Error::SessionExpired => write!(f, "session expired, please login again"),
+      Error::Server { status, url } => write!(f, "server responded with status {} ({})", status, url),
+      Error::SizeMismatch { url, expected, actual } => write!(f, "downloaded {} bytes, but expected {} bytes ({})", actual, expected, url),
     }
   }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Error::Network(e) => Some(e),
+      Error::Io(e) => Some(e),
+      Error::Json { source, .. } => Some(source),
+      _ => None,
+    }
+  }
+}
 
 /// A `Result` alias where the `Err` case is `crate::Error`.
 pub type Result<T> = std::result::Result<T, Error>;
 
-/// The owned `Id` type.
-pub type Id = String;
-/// The borrowed `Id` type.
-pub type IdRef<'a> = &'a str;
+// every kind of id is a distinct type, so that passing one kind of id where another is expected fails to compile
+macro_rules! ids {
+  ($($(#[$meta: meta])* $name: ident;)*) => {$(
+    $(#[$meta])*
+    ///
+    /// It is (de)serialized as a plain string, and it derefs to `String`.
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize, From, Deref)]
+    #[serde(transparent)]
+    pub struct $name(pub String);
+
+    impl From<&str> for $name {
+      fn from(s: &str) -> Self { Self(s.to_owned()) }
+    }
+
+    impl std::str::FromStr for $name {
+      type Err = std::convert::Infallible;
+      fn from_str(s: &str) -> std::result::Result<Self, Self::Err> { Ok(Self(s.to_owned())) }
+    }
+
+    impl PartialEq<str> for $name {
+      fn eq(&self, other: &str) -> bool { self.0 == other }
+    }
+
+    impl PartialEq<&str> for $name {
+      fn eq(&self, other: &&str) -> bool { self.0 == *other }
+    }
+
+    impl AsRef<str> for $name {
+      fn as_ref(&self) -> &str { &self.0 }
+    }
+
+    impl fmt::Display for $name {
+      fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str(&self.0) }
+    }
+  )*};
+}
+
+ids! {
+  /// The id of a semester, for example, "2019-2020-2". `Semester` parses it.
+  SemesterId;
+  /// `Course::id`.
+  CourseId;
+  /// `Notification::id`.
+  NotificationId;
+  /// `File::id`.
+  FileId;
+  /// `FileCategory::id`.
+  FileCategoryId;
+  /// `Homework::id`.
+  HomeworkId;
+  /// `Homework::student_homework_id`, which is different from `Homework::id`.
+  StudentHomeworkId;
+  /// `Discussion::id`.
+  DiscussionId;
+  /// `Discussion::board_id` or `DiscussionBoard::id`.
+  BoardId;
+  /// `DiscussionReply0::id`.
+  ReplyId;
+  /// `Question::id`.
+  QuestionId;
+}
 
 /// Constant id for fall semester. Please refer to `LearnHelper::semester_id_list`.
-pub const SEMESTER_FALL: u32 = 1;
+pub const SEMESTER_FALL: u32 = Term::Fall as u32;
 /// Constant id for spring semester. Please refer to `LearnHelper::semester_id_list`.
-pub const SEMESTER_SPRING: u32 = 2;
+pub const SEMESTER_SPRING: u32 = Term::Spring as u32;
 /// Constant id for summer semester. Please refer to `LearnHelper::semester_id_list`.
-pub const SEMESTER_SUMMER: u32 = 3;
+pub const SEMESTER_SUMMER: u32 = Term::Summer as u32;
+
+/// The term of a semester, the order is the order they happen in an academic year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Term {
+  /// "秋季学期", the last part of a semester id is "1".
+  Fall = 1,
+  /// "春季学期", the last part of a semester id is "2".
+  Spring = 2,
+  /// "夏季学期", the last part of a semester id is "3".
+  Summer = 3,
+}
+
+/// A semester parsed from its id, for example, "2019-2020-2" is the spring semester of academic year 2019-2020.
+///
+/// It is ordered chronologically, and it is serialized as its id. `to_string` gives the id back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Semester {
+  /// The year that the academic year starts, 2019 for "2019-2020-2".
+  pub start_year: u16,
+  /// The year that the academic year ends, it is always `start_year + 1`.
+  pub end_year: u16,
+  /// The term of the semester.
+  pub term: Term,
+}
+
+impl std::str::FromStr for Semester {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    let mut it = s.trim().split('-');
+    match (it.next().and_then(|x| x.parse::<u16>().ok()), it.next().and_then(|x| x.parse().ok()), it.next(), it.next()) {
+      (Some(start_year), Some(end_year), Some(term), None) if start_year.checked_add(1) == Some(end_year) => {
+        let term = match term { "1" => Term::Fall, "2" => Term::Spring, "3" => Term::Summer, _ => return Err(Error::Message("invalid semester id")) };
+        Ok(Semester { start_year, end_year, term })
+      }
+      _ => Err(Error::Message("invalid semester id")),
+    }
+  }
+}
+
+impl std::convert::TryFrom<String> for Semester {
+  type Error = Error;
+  fn try_from(s: String) -> Result<Self> { s.parse() }
+}
+
+impl From<Semester> for String {
+  fn from(s: Semester) -> String { s.to_string() }
+}
+
+impl From<Semester> for SemesterId {
+  fn from(s: Semester) -> SemesterId { SemesterId(s.to_string()) }
+}
+
+impl fmt::Display for Semester {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}-{}-{}", self.start_year, self.end_year, self.term as u32)
+  }
+}
+
+/// The current semester returned by `LearnHelper::current_semester`, together with its dates.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SemesterInfo {
+  /// The semester, `SemesterId::from(semester)` is the id used in parameters of `LearnHelper`, referred to as `semester: &SemesterId`.
+  #[serde(rename(deserialize = "xnxq"), alias = "semester")] pub semester: Semester,
+  /// The first day of the semester, it is normally a Monday.
+  #[serde(rename(deserialize = "kssj"), alias = "start_date")] pub start_date: NaiveDate,
+  /// The last day of the semester, including the exam weeks.
+  #[serde(rename(deserialize = "jssj"), alias = "end_date")] pub end_date: NaiveDate,
+}
+
+impl SemesterInfo {
+  /// The teaching week ("教学周") that `date` is in, counting from 1, weeks start on Monday.
+  /// It is `None` if `date` is not in the semester.
+  pub fn week(&self, date: NaiveDate) -> Option<u32> {
+    if date < self.start_date || date > self.end_date { return None; }
+    let monday = self.start_date - Duration::days(self.start_date.weekday().num_days_from_monday() as i64);
+    Some((date - monday).num_days() as u32 / 7 + 1)
+  }
+}
 
 /// Define the information of a course returned by web-learning.
-#[derive(Debug, Deserialize)]
+///
+/// Like the other types here, it serializes to the english field names,
+/// and it can be deserialized from both web-learning's response and its own serialized form.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Course {
-  /// Used in parameters of `LearnHelper`, referred to as `course: IdRef`.
-  #[serde(rename = "wlkcid")] pub id: Id,
+  /// Used in parameters of `LearnHelper`, referred to as `course: &CourseId`.
+  #[serde(rename(deserialize = "wlkcid"), alias = "id")] pub id: CourseId,
   /// The chinese name of this course, for example, "编译原理".
-  #[serde(rename = "kcm")] pub name: String,
+  #[serde(rename(deserialize = "kcm"), alias = "name")] pub name: String,
   /// The english name of this course, for example, "Principles and Practice of Compiler Construction".
-  #[serde(rename = "ywkcm")] pub english_name: String,
+  #[serde(rename(deserialize = "ywkcm"), alias = "english_name")] pub english_name: String,
   /// The name of the teacher of the course.
-  #[serde(rename = "jsm")] pub teacher_name: String,
+  #[serde(rename(deserialize = "jsm"), alias = "teacher_name")] pub teacher_name: String,
   /// `teacher_number` and `course_number` are normally string representation of an integer, but there are a few cases that they are not.
-  #[serde(rename = "jsh")] pub teacher_number: String,
+  #[serde(rename(deserialize = "jsh"), alias = "teacher_number")] pub teacher_number: String,
   /// Normally referred to as "课程号".
-  #[serde(rename = "kch")] pub course_number: String,
+  #[serde(rename(deserialize = "kch"), alias = "course_number")] pub course_number: String,
   /// Normally referred to as "课序号".
-  #[serde(rename = "kxh")] pub course_index: u32,
-  /// The time and location that the course is held.
+  #[serde(rename(deserialize = "kxh"), alias = "course_index")] pub course_index: u32,
+  /// The time and location that the course is held, for example, "星期一第2节(全周)，六教6A017". `time_slots` parses them.
   /// All courses have at least one time and location, and some may have two or more.
-  #[serde(skip)] pub time_location: Vec<String>,
+  #[serde(default)] pub time_location: Vec<String>,
 }
 
 impl Course {
-  /// The homepage url of the course that you see in the browser.
-  pub fn url(&self) -> String { COURSE_URL(&self.id) }
+  /// Parse `time_location`, in the same order.
+  pub fn time_slots(&self) -> Vec<TimeLocation> { self.time_location.iter().map(|x| TimeLocation::parse(x)).collect() }
+
+  /// The homepage url of the course that you see in the browser, `hosts` is usually `LearnHelper::hosts`.
+  pub fn url(&self, hosts: &Hosts) -> String { hosts.learn_url(&COURSE_URL(&self.id)) }
+
+  /// Same as `LearnHelper::notification_list(&self.id)`.
+  ///
+  /// The methods taking a `LearnHelper` are async, the blocking ones are suffixed with `_blocking`, for example, `Course::notification_list_blocking`.
+  pub async fn notification_list(&self, t: &LearnHelper) -> Result<Vec<Notification>> { t.notification_list(&self.id).await }
+
+  /// Same as `LearnHelper::file_list(&self.id)`.
+  pub async fn file_list(&self, t: &LearnHelper) -> Result<Vec<File>> { t.file_list(&self.id).await }
+
+  /// Same as `LearnHelper::file_categories(&self.id)`.
+  pub async fn file_categories(&self, t: &LearnHelper) -> Result<Vec<FileCategory>> { t.file_categories(&self.id).await }
+
+  /// Same as `LearnHelper::homework_list(&self.id)`.
+  pub async fn homework_list(&self, t: &LearnHelper) -> Result<Vec<Homework>> { t.homework_list(&self.id).await }
+
+  /// Same as `LearnHelper::discussion_list(&self.id)`.
+  pub async fn discussion_list(&self, t: &LearnHelper) -> Result<Vec<Discussion>> { t.discussion_list(&self.id).await }
+
+  /// Same as `LearnHelper::discussion_boards(&self.id)`.
+  pub async fn discussion_boards(&self, t: &LearnHelper) -> Result<Vec<DiscussionBoard>> { t.discussion_boards(&self.id).await }
+
+  /// Same as `LearnHelper::question_list(&self.id)`.
+  pub async fn question_list(&self, t: &LearnHelper) -> Result<Vec<Question>> { t.question_list(&self.id).await }
+
+  /// Same as `LearnHelper::discussion_replies`, for a discussion of this course.
+  pub async fn discussion_replies(&self, t: &LearnHelper, discussion: &Discussion) -> Result<Vec<DiscussionReply>> {
+    t.discussion_replies(&self.id, &discussion.id, &discussion.board_id).await
+  }
+}
+
+/// An element of `Course::time_location`, parsed by `Course::time_slots`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimeLocation {
+  /// The string is in the format of "星期一第2节(全周)，六教6A017".
+  Slot(TimeSlot),
+  /// The string is in an unknown format, it is kept unchanged.
+  Raw(String),
+}
+
+impl TimeLocation {
+  /// Parse a string in `Course::time_location`, it never fails, an unknown format gives `TimeLocation::Raw`.
+  pub fn parse(s: &str) -> Self { parse_time_slot(s).map_or_else(|| TimeLocation::Raw(s.to_owned()), TimeLocation::Slot) }
+}
+
+/// A class that is held at the same time every week (or every other week).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TimeSlot {
+  /// The day of the week, "星期一" is Monday.
+  pub weekday: Weekday,
+  /// The first "节" of the class, counting from 1. A day has 6 "大节".
+  pub first_period: u8,
+  /// The last "节" of the class, it is equal to `first_period` for "第2节".
+  pub last_period: u8,
+  /// The weeks that the class is held.
+  pub weeks: Weeks,
+  /// The classroom, for example, "六教6A017". Some classes (typically online ones) have no classroom.
+  pub classroom: Option<String>,
+}
+
+impl TimeSlot {
+  /// Whether the two classes are held at the same time in some week.
+  pub fn clashes(&self, other: &TimeSlot) -> bool {
+    self.weekday == other.weekday && self.first_period <= other.last_period && other.first_period <= self.last_period
+      && (1..=u8::MAX).any(|w| self.weeks.contains(w) && other.weeks.contains(w))
+  }
+}
+
+/// The weeks of a semester that a class is held.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Weeks {
+  /// "全周".
+  All,
+  /// "单周", the 1st, 3rd, 5th... week.
+  Odd,
+  /// "双周", the 2nd, 4th, 6th... week.
+  Even,
+  /// Inclusive ranges of weeks, counting from 1. "前八周" is `[(1, 8)]`, "第1-4,6周" is `[(1, 4), (6, 6)]`.
+  Explicit(Vec<(u8, u8)>),
+}
+
+impl Weeks {
+  /// Whether the class is held in the given week, counting from 1.
+  pub fn contains(&self, week: u8) -> bool {
+    match self {
+      Weeks::All => week >= 1,
+      Weeks::Odd => week % 2 == 1,
+      Weeks::Even => week >= 2 && week % 2 == 0,
+      Weeks::Explicit(ranges) => ranges.iter().any(|&(first, last)| first <= week && week <= last),
+    }
+  }
 }
 
 /// Define the information of a notification returned by web-learning.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Notification {
-  /// Used in parameters of `LearnHelper`, referred to as `course: IdRef`.
-  #[serde(rename = "wlkcid")] pub course_id: Id,
-  /// Used in parameters of `LearnHelper`, referred to as `notification: IdRef`.
-  #[serde(rename = "ggid")] pub id: Id,
+  /// Used in parameters of `LearnHelper`, referred to as `course: &CourseId`.
+  #[serde(rename(deserialize = "wlkcid"), alias = "course_id")] pub course_id: CourseId,
+  /// Used in parameters of `LearnHelper`, referred to as `notification: &NotificationId`.
+  #[serde(rename(deserialize = "ggid"), alias = "id")] pub id: NotificationId,
   /// The title of the notification.
-  #[serde(rename = "bt")] pub title: String,
+  #[serde(rename(deserialize = "bt"), alias = "title")] pub title: String,
   /// The content of the notification. It is a html string.
-  #[serde(rename = "ggnr", deserialize_with = "base64_string")] pub content: String,
+  #[serde(default)] pub content: String,
   /// Is this notification already read?
-  #[serde(rename = "sfyd", deserialize_with = "str_to_bool1")] pub read: bool,
+  #[serde(rename(deserialize = "sfyd"), alias = "read", deserialize_with = "str_to_bool1")] pub read: bool,
   /// Is this notification marked important by teacher?
-  #[serde(rename = "sfqd", deserialize_with = "str_to_bool2")] pub important: bool,
+  #[serde(rename(deserialize = "sfqd"), alias = "important", deserialize_with = "str_to_bool2")] pub important: bool,
   /// The publish time of the notification.
-  #[serde(rename = "fbsjStr", deserialize_with = "date_time")] pub publish_time: NaiveDateTime,
+  #[serde(rename(deserialize = "fbsjStr"), alias = "publish_time", deserialize_with = "date_time")] pub publish_time: NaiveDateTime,
   /// The publisher's name of the notification.
-  #[serde(rename = "fbrxm")] pub publisher: String,
+  #[serde(rename(deserialize = "fbrxm"), alias = "publisher")] pub publisher: String,
   /// When exists, it is the name of the attachment in the notification.
-  #[serde(rename = "fjmc")] pub attachment_name: Option<String>,
-  /// When exists, it is the url of the attachment in the notification.
-  /// Its existence should be the same as `attachment_name`, but I can't guarantee that.
-  #[serde(skip)] pub attachment_url: Option<String>,
+  #[serde(rename(deserialize = "fjmc"), alias = "attachment_name")] pub attachment_name: Option<String>,
+  /// When exists, it is the attachment in the notification, whose name is `attachment_name`.
+  #[serde(default)] pub attachment: Option<Attachment>,
 }
 
 impl Notification {
-  /// The detail page url of the notification that you see in the browser.
-  pub fn url(&self) -> String { NOTIFICATION_DETAIL(&self.id, &self.course_id) }
+  /// The detail page url of the notification that you see in the browser, `hosts` is usually `LearnHelper::hosts`.
+  pub fn url(&self, hosts: &Hosts) -> String { hosts.learn_url(&NOTIFICATION_DETAIL(&self.id, &self.course_id)) }
 }
 
 /// Define the information of a file returned by web-learning.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct File {
-  /// Used in parameters of `LearnHelper`, referred to as `file: IdRef`.
-  #[serde(rename = "wjid")] pub id: Id,
+  /// Used in parameters of `LearnHelper`, referred to as `file: &FileId`.
+  #[serde(rename(deserialize = "wjid"), alias = "id")] pub id: FileId,
   /// The title (or you may prefer to call it "name") of the file.
-  #[serde(rename = "bt")] pub title: String,
+  #[serde(rename(deserialize = "bt"), alias = "title")] pub title: String,
   /// The description of the file. It is a html string.
-  #[serde(rename = "ms")] pub description: String,
+  #[serde(rename(deserialize = "ms"), alias = "description")] pub description: String,
   /// Size in bytes.
-  #[serde(rename = "wjdx")] pub raw_size: u32,
+  #[serde(rename(deserialize = "wjdx"), alias = "raw_size")] pub raw_size: u32,
   /// Size description, for example, "1M".
-  #[serde(rename = "fileSize")] pub size: String,
+  #[serde(rename(deserialize = "fileSize"), alias = "size")] pub size: String,
   /// The time that the teacher uploaded this file.
-  #[serde(rename = "scsj", deserialize_with = "date_time")] pub upload_time: NaiveDateTime,
+  #[serde(rename(deserialize = "scsj"), alias = "upload_time", deserialize_with = "date_time")] pub upload_time: NaiveDateTime,
   /// Is this file **not** already read?
-  #[serde(rename = "isNew", deserialize_with = "int_to_bool")] pub new: bool,
+  #[serde(rename(deserialize = "isNew"), alias = "new", deserialize_with = "int_to_bool")] pub new: bool,
   /// Is this file marked important by teacher?
-  #[serde(rename = "sfqd", deserialize_with = "int_to_bool")] pub important: bool,
+  #[serde(rename(deserialize = "sfqd"), alias = "important", deserialize_with = "int_to_bool")] pub important: bool,
   /// The number of the students that have visited this file.
-  #[serde(rename = "llcs")] pub visit_count: u32,
+  #[serde(rename(deserialize = "llcs"), alias = "visit_count")] pub visit_count: u32,
   /// The number of the students that have downloaded this file.
-  #[serde(rename = "xzcs")] pub download_cunt: u32,
+  #[serde(rename(deserialize = "xzcs"), alias = "download_count")] pub download_count: u32,
   /// Suffix name of the file, for example, "zip", "ppt".
-  #[serde(rename = "wjlx")] pub file_type: String,
+  #[serde(rename(deserialize = "wjlx"), alias = "file_type")] pub file_type: String,
+  /// The category that the teacher put this file in, `None` if it is not in any category. Refer to `FileCategory::id`.
+  #[serde(rename(deserialize = "kjflid"), alias = "category_id", default, deserialize_with = "nonempty_string")] pub category_id: Option<FileCategoryId>,
 }
 
 impl File {
-  /// The url that starts download. You can feed it to `reqwest::Client` to download the file.
-  pub fn download_url(&self) -> String { FILE_DOWNLOAD(&self.id) }
+  /// The url that starts download, `hosts` is usually `LearnHelper::hosts`. `LearnHelper::download_file` downloads from it.
+  pub fn download_url(&self, hosts: &Hosts) -> String { hosts.learn_url(&FILE_DOWNLOAD(&self.id)) }
+
+  /// The category of this file in `categories`, which is usually the return value of `LearnHelper::file_categories`.
+  pub fn category<'a>(&self, categories: &'a [FileCategory]) -> Option<&'a FileCategory> {
+    let id = self.category_id.as_ref()?;
+    categories.iter().find(|x| x.id == *id)
+  }
+}
+
+/// Define the information of a file category (a folder that the teacher organizes the files of a course into) returned by web-learning.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FileCategory {
+  /// Used in parameters of `LearnHelper`, referred to as `category: &FileCategoryId`.
+  #[serde(rename(deserialize = "kjflid"), alias = "id")] pub id: FileCategoryId,
+  /// The title of the category.
+  #[serde(rename(deserialize = "bt"), alias = "title")] pub title: String,
+  /// The position of the category in web-learning, smaller ones come first.
+  #[serde(rename(deserialize = "xh"), alias = "order")] pub order: u32,
 }
 
-/// Define the information of a homework assignment returned by web-learning.
-#[derive(Debug, Deserialize, Deref, DerefMut)]
+/// Define the information of a homework assignment returned by web-learning. It is not `Eq` because `grade` is a float.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Deref, DerefMut)]
 pub struct Homework {
-  /// Used in parameters of `LearnHelper`, referred to as `course: IdRef`.
-  #[serde(rename = "wlkcid")] pub course_id: Id,
-  /// Used in parameters of `LearnHelper`, referred to as `homework: IdRef`.
-  #[serde(rename = "zyid")] pub id: Id,
-  /// Used in parameters of `LearnHelper`, referred to as `student_homework: IdRef`.
-  #[serde(rename = "xszyid")] pub student_homework_id: Id,
+  /// Used in parameters of `LearnHelper`, referred to as `course: &CourseId`.
+  #[serde(rename(deserialize = "wlkcid"), alias = "course_id")] pub course_id: CourseId,
+  /// Used in parameters of `LearnHelper`, referred to as `homework: &HomeworkId`.
+  #[serde(rename(deserialize = "zyid"), alias = "id")] pub id: HomeworkId,
+  /// Used in parameters of `LearnHelper`, referred to as `student_homework: &StudentHomeworkId`.
+  #[serde(rename(deserialize = "xszyid"), alias = "student_homework_id")] pub student_homework_id: StudentHomeworkId,
   /// The title (or you may prefer to call it "name") of the homework.
-  #[serde(rename = "bt")] pub title: String,
+  #[serde(rename(deserialize = "bt"), alias = "title")] pub title: String,
   /// The time that the teacher published the homework.
-  #[serde(rename = "kssjStr", deserialize_with = "date_time")] pub assign_time: NaiveDateTime,
+  #[serde(rename(deserialize = "kssjStr"), alias = "assign_time", deserialize_with = "date_time")] pub assign_time: NaiveDateTime,
   /// The time that the homework is due.
-  #[serde(rename = "jzsjStr", deserialize_with = "date_time")] pub deadline: NaiveDateTime,
+  #[serde(rename(deserialize = "jzsjStr"), alias = "deadline", deserialize_with = "date_time")] pub deadline: NaiveDateTime,
   /// When exists (when the student has submitted the homework), it is the time that the student submitted the homework.
-  #[serde(rename = "scsjStr", deserialize_with = "option_date_time")] pub submit_time: Option<NaiveDateTime>,
+  #[serde(rename(deserialize = "scsjStr"), alias = "submit_time", deserialize_with = "option_date_time")] pub submit_time: Option<NaiveDateTime>,
   /// When exists (when the student has submitted the homework), it is the content of the submitted homework.
   /// It is a html string.
-  #[serde(rename = "zynrStr", deserialize_with = "nonempty_string")] pub submit_content: Option<String>,
+  #[serde(rename(deserialize = "zynrStr"), alias = "submit_content", deserialize_with = "nonempty_string")] pub submit_content: Option<String>,
   /// When exists (when the teacher has graded the homework), it is the grade that the student received.
-  #[serde(rename = "cj")] pub grade: Option<f32>,
+  #[serde(rename(deserialize = "cj"), alias = "grade")] pub grade: Option<f32>,
   /// When exists (when the teacher has graded the homework), it is the time that the teacher graded the homework.
-  #[serde(rename = "pysjStr", deserialize_with = "option_date_time")] pub grade_time: Option<NaiveDateTime>,
+  #[serde(rename(deserialize = "pysjStr"), alias = "grade_time", deserialize_with = "option_date_time")] pub grade_time: Option<NaiveDateTime>,
   /// When exists (when the teacher has graded the homework), it is the name of the teacher that graded the homework.
-  #[serde(rename = "jsm", deserialize_with = "nonempty_string")] pub grader_name: Option<String>,
+  #[serde(rename(deserialize = "jsm"), alias = "grader_name", deserialize_with = "nonempty_string")] pub grader_name: Option<String>,
   /// When exists (when the teacher has graded the homework), it is comment by the teacher in the grade.
-  #[serde(rename = "pynr", deserialize_with = "nonempty_string")] pub grade_content: Option<String>,
+  #[serde(rename(deserialize = "pynr"), alias = "grade_content", deserialize_with = "nonempty_string")] pub grade_content: Option<String>,
+  /// Whether the homework is submitted or graded. It is computed when the homework is fetched, `status_at` gives the up-to-date value.
+  #[serde(default)] pub status: HomeworkStatus,
   /// Some extra fields of the homework.
-  #[serde(skip)]
+  #[serde(default)]
   #[deref]
   #[deref_mut]
   pub detail: HomeworkDetail,
 }
 
 impl Homework {
-  /// The detail page url of the homework that you see in the browser.
-  pub fn url(&self) -> String { HOMEWORK_DETAIL(&self.course_id, &self.id, &self.student_homework_id) }
+  /// The detail page url of the homework that you see in the browser, `hosts` is usually `LearnHelper::hosts`.
+  pub fn url(&self, hosts: &Hosts) -> String { hosts.learn_url(&HOMEWORK_DETAIL(&self.course_id, &self.id, &self.student_homework_id)) }
+
+  /// The page that you click "submit homework" in browser, `hosts` is usually `LearnHelper::hosts`.
+  pub fn submit_page(&self, hosts: &Hosts) -> String { hosts.learn_url(&HOMEWORK_SUBMIT_PAGE(&self.course_id, &self.student_homework_id)) }
+
+  /// Same as `LearnHelper::submit_homework(&self.student_homework_id, content, file)`.
+  /// The blocking version is `Homework::submit_blocking`.
+  pub async fn submit(&self, t: &LearnHelper, content: String, file: Option<(&str, Vec<u8>)>) -> Result<()> {
+    t.submit_homework(&self.student_homework_id, content, file).await
+  }
+
+  /// Same as `LearnHelper::resubmit_homework(self, content, attachment)`.
+  pub async fn resubmit(&self, t: &LearnHelper, content: String, attachment: AttachmentChange<'_>) -> Result<Homework> {
+    t.resubmit_homework(self, content, attachment).await
+  }
+
+  /// The status at `now` (in Beijing time, see `beijing_now`), only `NotSubmitted` and `Overdue` change over time.
+  pub fn status_at(&self, now: NaiveDateTime) -> HomeworkStatus {
+    use HomeworkStatus::*;
+    match self.status {
+      NotSubmitted | Overdue => if now > self.deadline { Overdue } else { NotSubmitted },
+      Submitted | LateSubmitted => if self.submit_time.map_or(false, |t| t > self.deadline) { LateSubmitted } else { Submitted },
+      Graded => Graded,
+    }
+  }
+
+  /// Whether it is not submitted yet, and the deadline is within `duration` from now.
+  pub fn due_within(&self, duration: std::time::Duration) -> bool {
+    let now = beijing_now();
+    // `to_std` fails only if the deadline has passed, when it is not `NotSubmitted` anyway
+    self.status_at(now) == HomeworkStatus::NotSubmitted && (self.deadline - now).to_std().map_or(false, |x| x <= duration)
+  }
+
+  // `list` is the index in `HOMEWORK_LIST_ALL` that the homework comes from
+  pub(crate) fn set_status(&mut self, list: usize) {
+    self.status = match list { 0 => HomeworkStatus::NotSubmitted, 1 => HomeworkStatus::Submitted, _ => HomeworkStatus::Graded };
+    self.status = self.status_at(beijing_now());
+  }
+}
+
+/// The status of a homework assignment, see `Homework::status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HomeworkStatus {
+  /// Not submitted yet, and the deadline has not passed.
+  NotSubmitted,
+  /// Not submitted yet, and the deadline has passed.
+  Overdue,
+  /// Submitted before the deadline, and not graded yet.
+  Submitted,
+  /// Submitted after the deadline, and not graded yet.
+  LateSubmitted,
+  /// Graded by the teacher.
+  Graded,
+}
 
-  /// The page that you click "submit homework" in browser.
-  pub fn submit_page(&self) -> String { HOMEWORK_SUBMIT_PAGE(&self.course_id, &self.student_homework_id) }
+impl Default for HomeworkStatus {
+  fn default() -> Self { HomeworkStatus::NotSubmitted }
+}
+
+/// The current time in Beijing time, which all the times from web-learning are in.
+pub fn beijing_now() -> NaiveDateTime { Utc::now().naive_utc() + Duration::hours(8) }
+
+/// What `LearnHelper::resubmit_homework` does to the attachment of the previous submission.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachmentChange<'a> {
+  /// Keep it as it is, or keep having no attachment.
+  Keep,
+  /// Replace it with `(file name, file content)`. File name is only used in web-learning.
+  Replace(&'a str, Vec<u8>),
+  /// Remove it.
+  Remove,
+}
+
+impl AttachmentChange<'_> {
+  // whether to remove the previous attachment, and the file to upload
+  pub(crate) fn form(&self) -> (bool, Option<(&str, Vec<u8>)>) {
+    match self {
+      AttachmentChange::Keep => (false, None),
+      AttachmentChange::Replace(name, data) => (false, Some((name, data.clone()))),
+      AttachmentChange::Remove => (true, None),
+    }
+  }
+
+  // `before` and `after` are `HomeworkDetail::submit_attachment` before and after the submission
+  pub(crate) fn check(&self, before: &Option<Attachment>, after: &Option<Attachment>) -> Result<()> {
+    let ok = match self {
+      AttachmentChange::Keep => before.as_ref().map(|x| &x.name) == after.as_ref().map(|x| &x.name),
+      AttachmentChange::Replace(name, _) => after.as_ref().map_or(false, |x| x.name == *name),
+      AttachmentChange::Remove => after.is_none(),
+    };
+    if ok { Ok(()) } else { Err("the attachment of the submission is not changed as requested".into()) }
+  }
 }
 
 /// It is always part of `Homework`, splitting it as a struct is only for convenience.
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
 pub struct HomeworkDetail {
   /// The description of the homework. It is a html string.
   pub description: String,
-  /// When exists, it is the `(name, url)` of the attachment of the homework.
-  pub attachment_name_url: Option<(String, String)>,
-  /// When exists, it is the `(name, url)` of the attachment of the submission of the homework.
-  pub submit_attachment_name_url: Option<(String, String)>,
-  /// When exists, it is the `(name, url)` of the attachment of the grade of the homework.
-  pub grade_attachment_name_url: Option<(String, String)>,
+  /// When exists, it is the attachment of the homework.
+  pub attachment: Option<Attachment>,
+  /// When exists, it is the attachment of the submission of the homework.
+  pub submit_attachment: Option<Attachment>,
+  /// When exists, it is the attachment of the grade of the homework.
+  pub grade_attachment: Option<Attachment>,
+}
+
+/// Define the information of an attachment of a notification or a homework, it can be downloaded by `LearnHelper::download_attachment`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Attachment {
+  /// The name of the attachment shown in the page, for example, "pa1.pdf".
+  pub name: String,
+  /// The url that starts download. It is a full url, i.e., it starts with `Hosts::learn`.
+  pub url: String,
+}
+
+/// The progress of a download, reported to the `progress` callback of `LearnHelper::download_file` and `LearnHelper::download_attachment`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadProgress {
+  /// The number of bytes in the destination, including the part that the download resumed from.
+  pub downloaded: u64,
+  /// When exists, it is the size of the whole file that the server claims.
+  pub total: Option<u64>,
+}
+
+/// The result of a finished download.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Downloaded {
+  /// When exists, it is the file name given by the server in the "Content-Disposition" header.
+  /// It can be different from `File::title` or `Attachment::name`, for example, it normally has a suffix.
+  pub file_name: Option<String>,
+  /// The size of the whole file, including the part that the download resumed from.
+  pub size: u64,
 }
 
 /// Define the information of a discussion returned by web-learning.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Discussion {
-  /// Used in parameters of `LearnHelper`, referred to as `discussion: IdRef`.
-  #[serde(rename = "id")] pub id: Id,
-  /// Used in parameters of `LearnHelper`, referred to as `discussion_board: IdRef`.
-  #[serde(rename = "bqid")] pub board_id: String,
+  /// Used in parameters of `LearnHelper`, referred to as `discussion: &DiscussionId`.
+  pub id: DiscussionId,
+  /// Used in parameters of `LearnHelper`, referred to as `discussion_board: &BoardId`.
+  #[serde(rename(deserialize = "bqid"), alias = "board_id")] pub board_id: BoardId,
   /// The title of the discussion.
-  #[serde(rename = "bt")] pub title: String,
+  #[serde(rename(deserialize = "bt"), alias = "title")] pub title: String,
   /// The name of the people that published the discussion.
   /// The content he published is regarded as the first reply to this discussion.
-  #[serde(rename = "fbrxm")] pub publisher_name: String,
+  #[serde(rename(deserialize = "fbrxm"), alias = "publisher_name")] pub publisher_name: String,
   /// The publish time of the discussion.
-  #[serde(rename = "fbsj", deserialize_with = "date_time1")] pub publish_time: NaiveDateTime,
+  #[serde(rename(deserialize = "fbsj"), alias = "publish_time", deserialize_with = "date_time")] pub publish_time: NaiveDateTime,
   /// The name of the last replier to this discussion.
-  #[serde(rename = "zhhfrxm", deserialize_with = "nonempty_string")] pub last_replier_name: Option<String>,
+  #[serde(rename(deserialize = "zhhfrxm"), alias = "last_replier_name", deserialize_with = "nonempty_string")] pub last_replier_name: Option<String>,
   /// The time that the last reply to this discussion was published.
-  #[serde(rename = "zhhfsj", deserialize_with = "option_date_time1")] pub last_reply_time: Option<NaiveDateTime>,
+  #[serde(rename(deserialize = "zhhfsj"), alias = "last_reply_time", deserialize_with = "option_date_time")] pub last_reply_time: Option<NaiveDateTime>,
   /// The number of the people that have visited this discussion.
-  #[serde(rename = "djs")] pub visit_count: u32,
+  #[serde(rename(deserialize = "djs"), alias = "visit_count")] pub visit_count: u32,
   /// The number of the people that have replied to this discussion.
-  #[serde(rename = "hfcs")] pub reply_count: u32,
+  #[serde(rename(deserialize = "hfcs"), alias = "reply_count")] pub reply_count: u32,
+}
+
+/// Define the information of a discussion board returned by web-learning, every discussion is in one of the boards of its course.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DiscussionBoard {
+  /// Used in parameters of `LearnHelper`, referred to as `board: &BoardId`.
+  #[serde(rename(deserialize = "bqid"), alias = "id")] pub id: BoardId,
+  /// The title of the board, for example, "课程讨论区".
+  #[serde(rename(deserialize = "bqmc"), alias = "title")] pub title: String,
 }
 
 /// Define the prototype of a discussion reply. Parameter `R` means the type of sub-replies.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DiscussionReply0<R> {
-  /// When exists, it is used in parameters of `LearnHelper`, referred to as `reply: IdRef`.
+  /// When exists, it is used in parameters of `LearnHelper`, referred to as `reply: &ReplyId`.
   /// The first reply is publisher's content, and cannot be further replied, so it doesn't have an `id`.
-  pub id: Option<String>,
+  pub id: Option<ReplyId>,
   /// The author name of the reply.
   pub author: String,
   /// The publish time of the reply.
   pub publish_time: NaiveDateTime,
   /// The content of the reply. It is a html string.
   pub content: String,
+  /// Is the reply published by the current user? Only such a reply can be edited or deleted.
+  /// For the first reply, it means that the discussion is published by the current user.
+  #[serde(default)] pub mine: bool,
   /// Sub-replies, `R` is `Vec<...>` if there is any, `()` if there is none
   pub replies: R,
 }
 
 /// The real discussion reply type in web-learning.
-pub type DiscussionReply = DiscussionReply0<Vec<DiscussionReply0<()>>>;
\ No newline at end of file
+pub type DiscussionReply = DiscussionReply0<Vec<DiscussionReply0<()>>>;
+
+/// The result of `LearnHelper::delete_discussion_reply`.
+#[derive(Debug)]
+pub enum DeleteOutcome {
+  /// The server reported success, or the reply is gone from `LearnHelper::discussion_replies`.
+  Confirmed,
+  /// The deletion timed out, and every fetch of the replies failed before the deadline, so it is unknown whether the reply is deleted.
+  /// It carries the error of the last fetch.
+  Assumed(Error),
+  /// The server refused the deletion, or the reply is still there when the deadline passes.
+  Failed,
+}
+
+/// Define the information of a question in the Q&A board ("课程答疑") of a course returned by web-learning.
+///
+/// The Q&A board is separate from the discussions, the students ask and the teacher or the TAs answer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Question {
+  /// Used in parameters of `LearnHelper`, referred to as `question: &QuestionId`.
+  pub id: QuestionId,
+  /// The board that the question belongs to, like `Discussion::board_id`.
+  #[serde(rename(deserialize = "bqid"), alias = "board_id")] pub board_id: BoardId,
+  /// The title of the question.
+  #[serde(rename(deserialize = "bt"), alias = "title")] pub title: String,
+  /// The name of the student that asked the question.
+  #[serde(rename(deserialize = "fbrxm"), alias = "publisher_name")] pub publisher_name: String,
+  /// The time that the question was asked.
+  #[serde(rename(deserialize = "fbsj"), alias = "publish_time", deserialize_with = "date_time")] pub publish_time: NaiveDateTime,
+  /// Has the question been answered by the teacher or the TAs?
+  #[serde(rename(deserialize = "sfhd"), alias = "answered", deserialize_with = "str_to_bool1")] pub answered: bool,
+  /// The time of the last answer to this question.
+  #[serde(rename(deserialize = "zhhfsj"), alias = "last_reply_time", deserialize_with = "option_date_time")] pub last_reply_time: Option<NaiveDateTime>,
+  /// The number of the people that have visited this question.
+  #[serde(rename(deserialize = "djs"), alias = "visit_count")] pub visit_count: u32,
+  /// The number of the answers to this question.
+  #[serde(rename(deserialize = "hfcs"), alias = "reply_count")] pub reply_count: u32,
+}
+
+/// The content and the answers of a question, returned by `LearnHelper::question_detail`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QuestionDetail {
+  /// The content of the question.
+  pub content: String,
+  /// The answers to the question, in the order they are published, the same as the replies of a discussion.
+  pub answers: Vec<DiscussionReply>,
+}
+
+impl QuestionDetail {
+  /// The first answer, which is normally the answer of the teacher. It is a html string.
+  pub fn answer(&self) -> Option<&str> { self.answers.first().map(|x| x.content.as_str()) }
+}
+
+/// A part of a course that `LearnHelper::semester_overview` fetches separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Category {
+  /// `Course::time_location`, it is always fetched.
+  TimeLocation,
+  /// `CourseSnapshot::notifications`.
+  Notifications,
+  /// `CourseSnapshot::files`.
+  Files,
+  /// `CourseSnapshot::homework`.
+  Homework,
+  /// `CourseSnapshot::discussions`.
+  Discussions,
+}
+
+/// Options of `LearnHelper::semester_overview`, the default fetches everything with at most 8 requests at the same time.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OverviewOptions {
+  /// The maximum number of requests in flight at the same time, 0 is treated as 1.
+  pub concurrency: usize,
+  /// Whether to fetch notifications, together with their attachments.
+  pub notifications: bool,
+  /// Whether to fetch files.
+  pub files: bool,
+  /// Whether to fetch homework assignments, together with their details.
+  pub homework: bool,
+  /// Whether to fetch discussions.
+  pub discussions: bool,
+}
+
+impl Default for OverviewOptions {
+  fn default() -> Self { Self { concurrency: 8, notifications: true, files: true, homework: true, discussions: true } }
+}
+
+/// Everything fetched of a course by `LearnHelper::semester_overview`.
+///
+/// A category is `None` when it is not requested in `OverviewOptions`, or when fetching it failed,
+/// in which case the error is in `errors`. A failed category never leaves partial data behind.
+#[derive(Debug)]
+pub struct CourseSnapshot {
+  /// The course itself, `Course::time_location` is empty if fetching it failed.
+  pub course: Course,
+  /// Same as the return value of `LearnHelper::notification_list`.
+  pub notifications: Option<Vec<Notification>>,
+  /// Same as the return value of `LearnHelper::file_list`.
+  pub files: Option<Vec<File>>,
+  /// Same as the return value of `LearnHelper::homework_list`.
+  pub homework: Option<Vec<Homework>>,
+  /// Same as the return value of `LearnHelper::discussion_list`.
+  pub discussions: Option<Vec<Discussion>>,
+  /// The first error of each failed category.
+  pub errors: Vec<(Category, Error)>,
+}
+
+impl CourseSnapshot {
+  pub(crate) fn new(course: Course) -> Self {
+    Self { course, notifications: None, files: None, homework: None, discussions: None, errors: Vec::new() }
+  }
+
+  /// The error that happened when fetching `category`, if any.
+  pub fn error(&self, category: Category) -> Option<&Error> {
+    self.errors.iter().find(|x| x.0 == category).map(|x| &x.1)
+  }
+
+  // drop whatever has been fetched of `category`, and keep only the first error of it
+  pub(crate) fn fail(&mut self, category: Category, e: Error) {
+    match category {
+      Category::TimeLocation => self.course.time_location.clear(),
+      Category::Notifications => self.notifications = None,
+      Category::Files => self.files = None,
+      Category::Homework => self.homework = None,
+      Category::Discussions => self.discussions = None,
+    }
+    if self.error(category).is_none() { self.errors.push((category, e)); }
+  }
+}
+
+/// The return value of `LearnHelper::semester_overview`.
+#[derive(Debug)]
+pub struct SemesterSnapshot {
+  /// The semester id passed to `LearnHelper::semester_overview`.
+  pub semester: SemesterId,
+  /// One snapshot for each course, in the same order as `LearnHelper::course_list`.
+  pub courses: Vec<CourseSnapshot>,
+}
+
+impl SemesterSnapshot {
+  /// All the errors in `courses`, together with the course they belong to.
+  pub fn errors(&self) -> impl Iterator<Item = (&Course, Category, &Error)> {
+    self.courses.iter().flat_map(|c| c.errors.iter().map(move |(category, e)| (&c.course, *category, e)))
+  }
+}
@@ -1,79 +1,128 @@
+use crate::PAGE_SIZE;
+
 pub const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/80.0.3987.149 Safari/537.36";
 
+// all the urls below are relative to `Hosts::id` or `Hosts::learn`, and `PREFIX` and `ID_PREFIX` are their default values
 pub const PREFIX: &str = "https://learn.tsinghua.edu.cn";
-pub const LOGIN: &str = "https://id.tsinghua.edu.cn/do/off/ui/auth/login/post/bb5df85216504820be7bba2b0ae1535b/0?/login.do";
+pub const ID_PREFIX: &str = "https://id.tsinghua.edu.cn";
+
+// relative to `Hosts::id`
+pub const LOGIN: &str = "/do/off/ui/auth/login/post/bb5df85216504820be7bba2b0ae1535b/0?/login.do";
+// the pages of the login form, which the login page may redirect to
+pub const ID_LOGIN_PAGES: &str = "/do/off/ui/auth/login/";
 
 pub fn AUTH_ROAM(ticket: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/b/j_spring_security_thauth_roaming_entry?ticket={}", ticket)
+  format!("/b/j_spring_security_thauth_roaming_entry?ticket={}", ticket)
 }
 
-pub const LOGOUT: &str = "https://learn.tsinghua.edu.cn/f/j_spring_security_logout";
-pub const SEMESTER_LIST: &str = "https://learn.tsinghua.edu.cn/b/wlxt/kc/v_wlkc_xs_xktjb_coassb/queryxnxq";
+pub const LOGOUT: &str = "/f/j_spring_security_logout";
+// where web-learning redirects to when the session is expired or not logged in at all
+pub const LOGIN_PAGE: &str = "/f/login";
+pub const SEMESTER_LIST: &str = "/b/wlxt/kc/v_wlkc_xs_xktjb_coassb/queryxnxq";
+pub const CURRENT_SEMESTER: &str = "/b/kc/zhjw_v_code_xnxq/getCurrentAndNextSemester";
 
 pub fn COURSE_LIST(semester: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/b/wlxt/kc/v_wlkc_xs_xkb_kcb_extend/student/loadCourseBySemesterId/{}", semester)
+  format!("/b/wlxt/kc/v_wlkc_xs_xkb_kcb_extend/student/loadCourseBySemesterId/{}", semester)
 }
 
 pub fn COURSE_URL(course: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/f/wlxt/index/course/student/course?wlkcid={}", course)
+  format!("/f/wlxt/index/course/student/course?wlkcid={}", course)
 }
 
 pub fn COURSE_TIME_LOCATION(course: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/b/kc/v_wlkc_xk_sjddb/detail?id={}", course)
+  format!("/b/kc/v_wlkc_xk_sjddb/detail?id={}", course)
 }
 
-pub fn FILE_LIST(course: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/b/wlxt/kj/wlkc_kjxxb/student/kjxxbByWlkcidAndSizeForStudent?wlkcid={}&size=200", course)
+// a course has only a few file categories, so it is not paginated
+pub fn FILE_CATEGORY_LIST(course: &str) -> String {
+  format!("/b/wlxt/kj/wlkc_kjflb/student/pageList?wlkcid={}", course)
+}
+
+// the list apis below are paginated, `page` starts from 1, and a page shorter than `PAGE_SIZE` is the last one
+pub fn FILE_LIST(course: &str, page: usize) -> String {
+  format!("/b/wlxt/kj/wlkc_kjxxb/student/kjxxbByWlkcidAndSizeForStudent?wlkcid={}&size={}&currentPage={}", course, PAGE_SIZE, page)
 }
 
 pub fn FILE_DOWNLOAD(file: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/b/wlxt/kj/wlkc_kjxxb/student/downloadFile?sfgk=0&wjid={}", file)
+  format!("/b/wlxt/kj/wlkc_kjxxb/student/downloadFile?sfgk=0&wjid={}", file)
 }
 
-pub fn NOTIFICATION_LIST(course: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/b/wlxt/kcgg/wlkc_ggb/student/kcggListXs?wlkcid={}&size=200", course)
+// what the browser posts when a file is opened
+pub fn FILE_MARK_READ(course: &str, file: &str) -> String {
+  format!("/b/wlxt/kj/wlkc_kjxxb/student/updateYdzt?wlkcid={}&wjid={}", course, file)
+}
+
+pub fn NOTIFICATION_LIST(course: &str, page: usize) -> String {
+  format!("/b/wlxt/kcgg/wlkc_ggb/student/kcggListXs?wlkcid={}&size={}&currentPage={}", course, PAGE_SIZE, page)
 }
 
 pub fn NOTIFICATION_DETAIL(notification: &str, course: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/f/wlxt/kcgg/wlkc_ggb/student/beforeViewXs?wlkcid={}&id={}", course, notification)
+  format!("/f/wlxt/kcgg/wlkc_ggb/student/beforeViewXs?wlkcid={}&id={}", course, notification)
+}
+
+// what the browser posts when a notification is opened
+pub fn NOTIFICATION_MARK_READ(course: &str, notification: &str) -> String {
+  format!("/b/wlxt/kcgg/wlkc_ggb/student/editYdzt?wlkcid={}&ggid={}", course, notification)
 }
 
-pub fn HOMEWORK_LIST_NEW(course: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/b/wlxt/kczy/zy/student/index/zyListWj?wlkcid={}&size=200", course)
+pub fn HOMEWORK_LIST_NEW(course: &str, page: usize) -> String {
+  format!("/b/wlxt/kczy/zy/student/index/zyListWj?wlkcid={}&size={}&currentPage={}", course, PAGE_SIZE, page)
 }
 
-pub fn HOMEWORK_LIST_SUBMITTED(course: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/b/wlxt/kczy/zy/student/index/zyListYjwg?wlkcid={}&size=200", course)
+pub fn HOMEWORK_LIST_SUBMITTED(course: &str, page: usize) -> String {
+  format!("/b/wlxt/kczy/zy/student/index/zyListYjwg?wlkcid={}&size={}&currentPage={}", course, PAGE_SIZE, page)
 }
 
-pub fn HOMEWORK_LIST_GRADED(course: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/b/wlxt/kczy/zy/student/index/zyListYpg?wlkcid={}&size=200", course)
+pub fn HOMEWORK_LIST_GRADED(course: &str, page: usize) -> String {
+  format!("/b/wlxt/kczy/zy/student/index/zyListYpg?wlkcid={}&size={}&currentPage={}", course, PAGE_SIZE, page)
 }
 
-pub const HOMEWORK_LIST_ALL: [fn(&str) -> String; 3] = [HOMEWORK_LIST_NEW, HOMEWORK_LIST_SUBMITTED, HOMEWORK_LIST_GRADED];
+pub const HOMEWORK_LIST_ALL: [fn(&str, usize) -> String; 3] = [HOMEWORK_LIST_NEW, HOMEWORK_LIST_SUBMITTED, HOMEWORK_LIST_GRADED];
 
 pub fn HOMEWORK_DETAIL(course: &str, homework: &str, student_homework: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/f/wlxt/kczy/zy/student/viewCj?wlkcid={}&zyid={}&xszyid={}", course, homework, student_homework)
+  format!("/f/wlxt/kczy/zy/student/viewCj?wlkcid={}&zyid={}&xszyid={}", course, homework, student_homework)
 }
 
 // the page that you click "submit homework" in browser, not really used in submitting homework
 pub fn HOMEWORK_SUBMIT_PAGE(course: &str, student_homework: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/f/wlxt/kczy/zy/student/tijiao?wlkcid={}&xszyid={}", course, student_homework)
+  format!("/f/wlxt/kczy/zy/student/tijiao?wlkcid={}&xszyid={}", course, student_homework)
 }
 
 // the url that really performs submitting
-pub const HOMEWORK_SUBMIT: &str = "https://learn.tsinghua.edu.cn/b/wlxt/kczy/zy/student/tjzy";
+pub const HOMEWORK_SUBMIT: &str = "/b/wlxt/kczy/zy/student/tjzy";
 
-pub fn DISCUSSION_LIST(course: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/b/wlxt/bbs/bbs_tltb/student/kctlList?wlkcid={}&size=200", course)
+pub fn DISCUSSION_LIST(course: &str, page: usize) -> String {
+  format!("/b/wlxt/bbs/bbs_tltb/student/kctlList?wlkcid={}&size={}&currentPage={}", course, PAGE_SIZE, page)
 }
 
 pub fn DISCUSSION_REPLIES(course: &str, discussion: &str, discussion_board: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/f/wlxt/bbs/bbs_tltb/student/viewTlById?wlkcid={}&id={}&bqid={}&tabbh=1", course, discussion, discussion_board)
+  format!("/f/wlxt/bbs/bbs_tltb/student/viewTlById?wlkcid={}&id={}&bqid={}&tabbh=1", course, discussion, discussion_board)
+}
+
+pub const REPLY_DISCUSSION: &str = "/b/wlxt/bbs/bbs_tltb/student/saveEdit";
+
+// the boards are few, so the list is not paginated
+pub fn DISCUSSION_BOARD_LIST(course: &str) -> String {
+  format!("/b/wlxt/bbs/bbs_kcbq/student/kcbqList?wlkcid={}", course)
+}
+
+// both creating and editing a discussion, the latter has the `id` field
+pub const SAVE_DISCUSSION: &str = "/b/wlxt/bbs/bbs_tltb/student/saveTl";
+
+pub fn DELETE_DISCUSSION(course: &str, discussion: &str) -> String {
+  format!("/b/wlxt/bbs/bbs_tltb/student/delTl?wlkcid={}&id={}", course, discussion)
+}
+
+pub fn QUESTION_LIST(course: &str, page: usize) -> String {
+  format!("/b/wlxt/bbs/bbs_kcdy/student/kcdyList?wlkcid={}&size={}&currentPage={}", course, PAGE_SIZE, page)
+}
+
+pub fn QUESTION_DETAIL(course: &str, question: &str) -> String {
+  format!("/f/wlxt/bbs/bbs_kcdy/student/viewDyById?wlkcid={}&id={}", course, question)
 }
 
-pub const REPLY_DISCUSSION: &str = "https://learn.tsinghua.edu.cn/b/wlxt/bbs/bbs_tltb/student/saveEdit";
+pub const ASK_QUESTION: &str = "/b/wlxt/bbs/bbs_kcdy/student/saveDy";
 
 pub fn DELETE_DISCUSSION_REPLY(course: &str, reply: &str) -> String {
-  format!("https://learn.tsinghua.edu.cn/b/wlxt/bbs/bbs_hhb/student/delHf?wlkcid={}&id={}", course, reply)
+  format!("/b/wlxt/bbs/bbs_hhb/student/delHf?wlkcid={}&id={}", course, reply)
 }
\ No newline at end of file
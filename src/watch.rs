@@ -0,0 +1,149 @@
+use chrono::NaiveDateTime;
+use serde::{Serialize, Deserialize};
+use std::{collections::{BTreeMap, BTreeSet}, fs, io, path::PathBuf};
+use crate::{parse::*, types::*};
+
+/// A change that `LearnHelper::watch` finds between two polls. Every variant except `Failed` carries the course it happens in.
+#[derive(Debug)]
+pub enum Event {
+  /// A notification is published.
+  NewNotification(Course, Notification),
+  /// A file is uploaded.
+  NewFile(Course, File),
+  /// A file is uploaded again, i.e., its `File::upload_time` or `File::raw_size` changed.
+  FileUpdated(Course, File),
+  /// A homework is assigned.
+  HomeworkAssigned(Course, Homework),
+  /// The deadline of a homework is changed, the last one is the old deadline.
+  DeadlineChanged(Course, Homework, NaiveDateTime),
+  /// A homework is graded.
+  HomeworkGraded(Course, Homework),
+  /// A discussion has new replies, i.e., its `Discussion::reply_count` increased.
+  NewDiscussionReply(Course, Discussion),
+  /// Fetching a category of a course failed, or the whole poll failed if it is `None`.
+  /// The state of what failed is kept, so the changes in it are reported by a later poll.
+  Failed(Option<(Course, Category)>, Error),
+}
+
+/// What a watch remembers from the last poll, the next poll is compared with it to find the events.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchState {
+  /// `Course::id => the state of the course`.
+  pub courses: BTreeMap<CourseId, CourseState>,
+}
+
+/// The state of a course in `WatchState`.
+///
+/// A category is `None` until it is fetched successfully for the first time, and that fetch emits no events,
+/// so that a new watch (or a new course) doesn't report everything as new.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CourseState {
+  /// The `Notification::id`s.
+  pub notifications: Option<BTreeSet<NotificationId>>,
+  /// `File::id => (File::upload_time, File::raw_size)`.
+  pub files: Option<BTreeMap<FileId, (NaiveDateTime, u32)>>,
+  /// `Homework::id => (Homework::deadline, whether it is graded)`.
+  pub homework: Option<BTreeMap<HomeworkId, (NaiveDateTime, bool)>>,
+  /// `Discussion::id => Discussion::reply_count`.
+  pub discussions: Option<BTreeMap<DiscussionId, u32>>,
+}
+
+/// Where a watch keeps its `WatchState` between polls, a persistent store keeps a restarted watch from emitting the old events again.
+pub trait StateStore {
+  /// Read the saved state, return the default state if nothing is saved.
+  fn load(&mut self) -> Result<WatchState>;
+  /// Replace the saved state with `state`, it is called after every poll.
+  fn save(&mut self, state: &WatchState) -> Result<()>;
+}
+
+impl<S: StateStore + ?Sized> StateStore for &mut S {
+  fn load(&mut self) -> Result<WatchState> { (**self).load() }
+  fn save(&mut self, state: &WatchState) -> Result<()> { (**self).save(state) }
+}
+
+/// A `StateStore` in memory, which is lost when the program exits.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore(pub WatchState);
+
+impl StateStore for MemoryStore {
+  fn load(&mut self) -> Result<WatchState> { Ok(self.0.clone()) }
+  fn save(&mut self, state: &WatchState) -> Result<()> {
+    self.0 = state.clone();
+    Ok(())
+  }
+}
+
+/// A `StateStore` in a json file at the path, which is created when the state is saved for the first time.
+#[derive(Debug, Clone)]
+pub struct FileStore(pub PathBuf);
+
+impl StateStore for FileStore {
+  fn load(&mut self) -> Result<WatchState> {
+    match fs::read_to_string(&self.0) {
+      Ok(text) => parse_json(self.0.display().to_string(), &text),
+      Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(WatchState::default()),
+      Err(e) => Err(e.into()),
+    }
+  }
+
+  fn save(&mut self, state: &WatchState) -> Result<()> {
+    Ok(fs::write(&self.0, serde_json::to_string_pretty(state).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?)?)
+  }
+}
+
+// a poll of the async api and the blocking api, the categories missing from `snapshot` keep their state
+pub(crate) fn poll(store: &mut impl StateStore, snapshot: Result<SemesterSnapshot>) -> Vec<Event> {
+  let res = snapshot.and_then(|snapshot| Ok((store.load()?, snapshot)));
+  let (mut state, snapshot) = match res { Ok(x) => x, Err(e) => return vec![Event::Failed(None, e)] };
+  let mut events = diff(&mut state, snapshot);
+  if let Err(e) = store.save(&state) { events.push(Event::Failed(None, e)); }
+  events
+}
+
+// compare `snapshot` with `state`, update `state` to it and return the changes
+fn diff(state: &mut WatchState, snapshot: SemesterSnapshot) -> Vec<Event> {
+  use Event::*;
+  let mut events = Vec::new();
+  state.courses.retain(|id, _| snapshot.courses.iter().any(|c| c.course.id == *id));
+  for c in snapshot.courses {
+    let (course, s) = (&c.course, state.courses.entry(c.course.id.clone()).or_default());
+    for (category, e) in c.errors { events.push(Failed(Some((course.clone(), category)), e)); }
+    if let Some(ns) = c.notifications {
+      let old = s.notifications.replace(ns.iter().map(|x| x.id.clone()).collect());
+      if let Some(old) = old {
+        events.extend(ns.into_iter().filter(|x| !old.contains(&x.id)).map(|x| NewNotification(course.clone(), x)));
+      }
+    }
+    if let Some(fs) = c.files {
+      let old = s.files.replace(fs.iter().map(|x| (x.id.clone(), (x.upload_time, x.raw_size))).collect());
+      for f in fs.into_iter().filter(|_| old.is_some()) {
+        match old.as_ref().and_then(|old| old.get(&f.id)) {
+          None => events.push(NewFile(course.clone(), f)),
+          Some(&x) if x != (f.upload_time, f.raw_size) => events.push(FileUpdated(course.clone(), f)),
+          _ => {}
+        }
+      }
+    }
+    if let Some(hs) = c.homework {
+      let old = s.homework.replace(hs.iter().map(|x| (x.id.clone(), (x.deadline, x.status == HomeworkStatus::Graded))).collect());
+      for h in hs.into_iter().filter(|_| old.is_some()) {
+        // both the deadline and the grade may change between two polls, and both are reported
+        match old.as_ref().and_then(|old| old.get(&h.id)) {
+          None => events.push(HomeworkAssigned(course.clone(), h)),
+          Some(&(deadline, graded)) => {
+            if deadline != h.deadline { events.push(DeadlineChanged(course.clone(), h.clone(), deadline)); }
+            if !graded && h.status == HomeworkStatus::Graded { events.push(HomeworkGraded(course.clone(), h)); }
+          }
+        }
+      }
+    }
+    if let Some(ds) = c.discussions {
+      let old = s.discussions.replace(ds.iter().map(|x| (x.id.clone(), x.reply_count)).collect());
+      if let Some(old) = old {
+        // a new discussion is not reported, only the replies to a known one are
+        events.extend(ds.into_iter().filter(|x| old.get(&x.id).map_or(false, |&n| x.reply_count > n)).map(|x| NewDiscussionReply(course.clone(), x)));
+      }
+    }
+  }
+  events
+}
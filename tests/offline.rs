@@ -0,0 +1,537 @@
+use std::sync::Arc;
+use serde_json::json;
+use futures::{StreamExt, TryStreamExt};
+use thu_learn_helper::{LearnHelper, DELETE_DR_DEADLINE, PAGE_SIZE, export::ical::Calendar, Credentials, Session, SessionState, render::Render, test_server::*, types::*};
+
+fn semester() -> SemesterId { SEMESTER.into() }
+
+fn course() -> CourseId { COURSE.into() }
+
+async fn login(server: &TestServer) -> LearnHelper {
+  LearnHelper::login_to(server.hosts(), USERNAME, PASSWORD).await.unwrap()
+}
+
+#[tokio::test]
+async fn login_failure() {
+  let server = TestServer::start();
+  match LearnHelper::login_to(server.hosts(), USERNAME, "wrong password").await { Err(Error::Auth) => {}, _ => panic!() }
+}
+
+#[tokio::test]
+async fn json_error() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  match t.course_list(&"malformed semester id".into()).await {
+    Err(Error::Json { endpoint, body_excerpt, .. }) => {
+      assert!(endpoint.starts_with(&(server.hosts().learn + "/b/wlxt/kc/")));
+      assert_eq!(body_excerpt, "<html><body>系统错误</body></html>");
+    }
+    _ => panic!(),
+  }
+}
+
+#[tokio::test]
+async fn session_expired() {
+  let server = TestServer::start();
+  let t = LearnHelper::restore_session(SessionState { hosts: server.hosts(), session: Session::default() }).unwrap(); // not logged in at all
+  match t.semester_id_list().await { Err(Error::SessionExpired) => {}, _ => panic!() }
+  let t = login(&server).await;
+  server.expire_sessions();
+  match t.semester_id_list().await { Err(Error::SessionExpired) => {}, _ => panic!() }
+  // only the login pages themselves mean an expired session, not every url with "login" in it
+  let t = login(&server).await;
+  server.redirect_requests("/b/wlxt/kc/v_wlkc_xs_xktjb_coassb/queryxnxq", "/b/wlxt/kczy/zy/student/downloadFile/c1/login");
+  match t.semester_id_list().await { Err(Error::Server { status: 404, url }) => assert!(url.ends_with("/c1/login")), _ => panic!() }
+  server.redirect_requests("/b/kc/zhjw_v_code_xnxq/getCurrentAndNextSemester", "/f/login?from=semester");
+  match t.current_semester().await { Err(Error::SessionExpired) => {}, _ => panic!() }
+}
+
+#[tokio::test]
+async fn save_and_restore_session() {
+  let server = TestServer::start();
+  let state = login(&server).await.save_session();
+  assert!(state.session.cookies.contains_key("JSESSIONID") && state.session.login_time.is_some());
+  let state = serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+  let t = LearnHelper::restore_session(state).unwrap();
+  assert!(t.is_session_valid().await.unwrap());
+  assert_eq!(t.file_list(&course()).await.unwrap().len(), 2);
+  server.expire_sessions();
+  assert!(!t.is_session_valid().await.unwrap());
+}
+
+#[tokio::test]
+async fn relogin() {
+  let server = TestServer::start();
+  let t = LearnHelper::login_with(server.hosts(), Credentials::Stored { username: USERNAME.to_owned(), password: PASSWORD.to_owned() }).await.unwrap();
+  server.expire_sessions();
+  assert_eq!(t.semester_id_list().await.unwrap().len(), 2);
+  server.expire_sessions();
+  t.submit_homework(&"s1".into(), "my answer".to_owned(), Some(("answer.txt", b"42".to_vec()))).await.unwrap();
+  let body = server.requests().pop().unwrap().body;
+  assert!(String::from_utf8_lossy(&body).contains("answer.txt")); // the form is rebuilt for the retry
+  let t = LearnHelper::login_with(server.hosts(), Credentials::Provider(Arc::new(|| None))).await;
+  match t { Err(Error::Auth) => {}, _ => panic!() }
+  // the concurrent requests of an overview find the session expired together, and only one of them logins again
+  let t = LearnHelper::login_with(server.hosts(), Credentials::Stored { username: USERNAME.to_owned(), password: PASSWORD.to_owned() }).await.unwrap();
+  let logins = || server.requests().iter().filter(|r| r.path.starts_with("/do/off/ui/auth/login/post/")).count();
+  let before = logins();
+  server.expire_sessions_after("/b/wlxt/kc/v_wlkc_xs_xkb_kcb_extend/student/loadCourseBySemesterId/");
+  let s = t.semester_overview(&semester(), &Default::default()).await.unwrap();
+  assert!(s.courses.iter().all(|c| c.errors.is_empty()));
+  assert_eq!(logins(), before + 1);
+}
+
+#[tokio::test]
+async fn semesters_and_courses() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  assert_eq!(t.semester_id_list().await.unwrap(), ["2019-2020-2", "2019-2020-1"]);
+  let s = t.current_semester().await.unwrap();
+  assert_eq!((s.semester.to_string().as_str(), s.semester.term), (SEMESTER, Term::Spring));
+  let date = |m, d| chrono::NaiveDate::from_ymd_opt(2020, m, d).unwrap();
+  assert_eq!((s.week(date(2, 16)), s.week(date(2, 17)), s.week(date(2, 23)), s.week(date(2, 24)), s.week(date(6, 22))), (None, Some(1), Some(1), Some(2), None));
+  let mut ss = ["2019-2020-1", "2018-2019-3", "2019-2020-3", "2019-2020-2"].iter().map(|x| x.parse::<Semester>().unwrap()).collect::<Vec<_>>();
+  ss.sort();
+  assert_eq!(ss.iter().map(|x| x.to_string()).collect::<Vec<_>>(), ["2018-2019-3", "2019-2020-1", "2019-2020-2", "2019-2020-3"]);
+  assert!(["2019-2020-4", "2019-2021-1", "2019-2020", "autumn", "65535-65536-1", "65535-0-1"].iter().all(|x| x.parse::<Semester>().is_err()));
+  assert_eq!(serde_json::to_value(&s).unwrap(), json!({"semester": SEMESTER, "start_date": "2020-02-17", "end_date": "2020-06-21"}));
+  let cs = t.course_list(&semester()).await.unwrap();
+  assert_eq!(cs.len(), 2);
+  assert_eq!((cs[0].id.as_str(), cs[0].name.as_str(), cs[0].course_index), (COURSE, "编译原理", 0));
+  assert_eq!(cs[0].time_location, ["星期一第2节(全周)，六教6A017", "星期三第4节(单周)，六教6A017"]);
+  assert!(cs[1].time_location.is_empty());
+  match &cs[0].time_slots()[1] { TimeLocation::Slot(x) => assert_eq!((x.first_period, x.weeks.contains(2)), (4, false)), x => panic!("{:?}", x) }
+  assert!(t.course_list(&"2019-2020-1".into()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn notifications_and_files() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  let ns = t.notification_list(&course()).await.unwrap();
+  assert_eq!(ns.len(), 2);
+  assert_eq!(ns[0].content, "<p>第一次课在六教6A017。</p>");
+  assert!(ns[0].read && ns[0].important && ns[0].attachment.is_none());
+  assert!(!ns[1].read && !ns[1].important);
+  let a = ns[1].attachment.as_ref().unwrap();
+  assert_eq!((a.name.as_str(), a.url.clone()), ("lab.pdf", server.hosts().learn + "/b/wlxt/kj/wlkc_kjxxb/student/downloadFile?sfgk=0&wjid=a6"));
+  let fs = t.file_list(&course()).await.unwrap();
+  assert_eq!(fs.len(), 2);
+  assert_eq!((fs[0].raw_size, fs[0].new, fs[0].important), (1048576, false, true));
+  assert_eq!((fs[1].file_type.as_str(), fs[1].new, fs[1].important), ("zip", true, false));
+  assert!(t.file_list(&"c2".into()).await.unwrap().is_empty());
+  let cs = t.file_categories(&course()).await.unwrap();
+  assert_eq!(cs.iter().map(|x| (x.id.as_str(), x.title.as_str())).collect::<Vec<_>>(), [("k1", "课件"), ("k2", "实验")]);
+  assert_eq!((fs[0].category(&cs).map(|x| x.title.as_str()), fs[1].category_id.as_ref()), (Some("课件"), None));
+  assert!(t.file_categories(&"c2".into()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn mark_read() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  t.mark_notification_read(&course(), &"n1".into()).await.unwrap();
+  t.mark_file_read(&course(), &"f1".into()).await.unwrap();
+  assert!(t.mark_file_read(&course(), &"n1".into()).await.is_err());
+  assert_eq!(t.mark_all_read(&course()).await.unwrap(), 2); // "n2" and "f2"
+  let marked = server.requests().into_iter().filter(|r| r.method == "POST" && r.path.contains("Ydzt")).map(|r| r.path).collect::<Vec<_>>();
+  assert!(marked.iter().any(|x| x.ends_with("ggid=n2")) && marked.iter().any(|x| x.ends_with("wjid=f2")) && marked.len() == 5);
+}
+
+#[tokio::test]
+async fn serialize() {
+  use serde::{Serialize, de::DeserializeOwned};
+  fn round_trip<T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug>(x: Vec<T>) -> serde_json::Value {
+    let json = serde_json::to_string(&x).unwrap();
+    assert_eq!(serde_json::from_str::<Vec<T>>(&json).unwrap(), x);
+    serde_json::from_str(&json).unwrap()
+  }
+  let server = TestServer::start();
+  let t = login(&server).await;
+  let cs = round_trip(t.course_list(&semester()).await.unwrap());
+  assert_eq!((&cs[0]["id"], &cs[0]["name"], &cs[0]["time_location"][1]), (&json!("c1"), &json!("编译原理"), &json!("星期三第4节(单周)，六教6A017")));
+  let ns = round_trip(t.notification_list(&course()).await.unwrap());
+  assert_eq!((&ns[0]["content"], &ns[0]["read"], &ns[0]["publish_time"]), (&json!("<p>第一次课在六教6A017。</p>"), &json!(true), &json!("2020-02-17T09:00:00")));
+  assert_eq!(ns[1]["attachment"]["name"], json!("lab.pdf"));
+  let fs = round_trip(t.file_list(&course()).await.unwrap());
+  assert!(fs[0].get("wjid").is_none() && fs[0]["download_count"] == json!(98));
+  let hs = round_trip(t.homework_list(&course()).await.unwrap());
+  assert_eq!((&hs[2]["grade"], &hs[2]["detail"]["grade_attachment"]["name"]), (&json!(95.0), &json!("pa0-comment.pdf")));
+  let ds = t.discussion_list(&course()).await.unwrap();
+  let rs = t.discussion_replies(&course(), &ds[0].id, &ds[0].board_id).await.unwrap();
+  round_trip(ds);
+  assert_eq!(round_trip(rs)[1]["replies"][0]["author"], json!("张三"));
+  assert_eq!(round_trip(vec![t.current_semester().await.unwrap()])[0]["semester"], json!(SEMESTER));
+}
+
+#[tokio::test]
+async fn download() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  let mut fs = t.file_list(&course()).await.unwrap();
+  let (mut out, mut last) = (Vec::new(), None);
+  let res = t.download_file(&fs[0], 0, &mut out, |p| last = Some(p)).await.unwrap();
+  assert_eq!((res.file_name.as_deref(), res.size), (Some("第一讲.pdf"), 1048576));
+  assert_eq!(out, file_content("f1").unwrap());
+  let last = last.unwrap();
+  assert_eq!((last.downloaded, last.total), (1048576, Some(1048576)));
+  // resume with "Range"
+  let mut out = file_content("f2").unwrap()[..1000].to_vec();
+  let res = t.download_file(&fs[1], 1000, &mut out, |_| {}).await.unwrap();
+  assert_eq!((res.file_name.as_deref(), res.size), (Some("实验框架.zip"), 20480));
+  assert_eq!(out, file_content("f2").unwrap());
+  let res = t.download_file(&fs[1], 20480, &mut out, |_| {}).await.unwrap(); // already complete
+  assert_eq!(res.size, 20480);
+  fs[1].raw_size += 1;
+  match t.download_file(&fs[1], 0, Vec::new(), |_| {}).await { Err(Error::SizeMismatch { expected: 20481, actual: 20480, .. }) => {}, _ => panic!() }
+  // attachments ignore "Range", the part that already exists is skipped
+  let hs = t.homework_list(&course()).await.unwrap();
+  let mut out = file_content("a1").unwrap()[..300].to_vec();
+  let res = t.download_attachment(hs[0].attachment.as_ref().unwrap(), 300, &mut out, |_| {}).await.unwrap();
+  assert_eq!((res.file_name.as_deref(), res.size), (Some("a1.bin"), 1000));
+  assert_eq!(out, file_content("a1").unwrap());
+}
+
+#[tokio::test]
+async fn sync() {
+  use std::fs;
+  use thu_learn_helper::sync::{Manifest, MANIFEST};
+  let server = TestServer::start();
+  let t = login(&server).await;
+  let root = std::env::temp_dir().join(format!("thu-learn-helper-sync-{}", std::process::id()));
+  let _ = fs::remove_dir_all(&root);
+  let res = t.sync_semester(&root, &semester()).await.unwrap();
+  assert_eq!((res.added.len(), res.updated.len(), res.removed.len()), (7, 0, 0)); // 2 files, 3 homework, 2 notifications
+  assert_eq!(fs::read(root.join("编译原理/files/课件/第一讲.pdf")).unwrap(), file_content("f1").unwrap());
+  assert!(root.join("编译原理/files/实验框架.zip").exists());
+  assert_eq!(fs::read(root.join("编译原理/homework/PA0/pa0-comment.pdf")).unwrap(), file_content("a5").unwrap());
+  assert_eq!(fs::read(root.join("编译原理/notifications/实验说明/lab.pdf")).unwrap(), file_content("a6").unwrap());
+  assert!(fs::read_to_string(root.join("编译原理/homework/PA0/homework.html")).unwrap().contains("95"));
+  let res = t.sync_semester(&root, &semester()).await.unwrap();
+  assert!(res.added.is_empty() && res.updated.is_empty() && res.removed.is_empty());
+  // a file deleted by hand, a file changed in web-learning, and a file removed from web-learning
+  fs::remove_file(root.join("编译原理/files/实验框架.zip")).unwrap();
+  let mut manifest = Manifest::load(&root).unwrap();
+  manifest.entries.get_mut("f1").unwrap().size += 1;
+  // the part file left by an interrupted download of an older version of the homework is not resumed
+  manifest.entries.get_mut("h3").unwrap().hash += 1;
+  fs::write(root.join("编译原理/homework/PA0/pa0-comment.pdf.part"), "old version").unwrap();
+  let mut removed = manifest.entries["f1"].clone();
+  removed.paths = vec!["编译原理/files/old.pdf".into()];
+  fs::write(root.join("编译原理/files/old.pdf"), "old").unwrap();
+  manifest.entries.insert("f0".to_owned(), removed);
+  manifest.save(&root).unwrap();
+  let res = t.sync_semester(&root, &semester()).await.unwrap();
+  let ids = |x: &[thu_learn_helper::sync::SyncItem]| x.iter().map(|x| x.id.clone()).collect::<Vec<_>>();
+  assert_eq!((ids(&res.added), ids(&res.updated), ids(&res.removed)), (vec![], vec!["f1".to_owned(), "f2".to_owned(), "h3".to_owned()], vec!["f0".to_owned()]));
+  assert_eq!(fs::read(root.join("编译原理/homework/PA0/pa0-comment.pdf")).unwrap(), file_content("a5").unwrap());
+  assert!(root.join("编译原理/files/实验框架.zip").exists() && !root.join("编译原理/files/old.pdf").exists());
+  assert!(root.join(MANIFEST).exists());
+  fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn homework() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  let hs = t.homework_list(&course()).await.unwrap();
+  let ids = hs.iter().map(|h| h.id.as_str()).collect::<Vec<_>>();
+  assert_eq!(ids, ["h1", "h2", "h3"]);
+  assert!(hs[0].submit_time.is_none() && hs[0].grader_name.is_none());
+  assert_eq!(hs[0].attachment.as_ref().unwrap().name, "pa1.pdf");
+  assert_eq!(hs[1].submit_content.as_deref(), Some("<p>见附件</p>"));
+  assert!(hs[1].grader_name.is_none()); // empty string in response
+  assert_eq!(hs[2].grade, Some(95.0));
+  assert_eq!(hs[2].grade_attachment.as_ref().unwrap().name, "pa0-comment.pdf");
+  let status = hs.iter().map(|h| h.status).collect::<Vec<_>>();
+  assert_eq!(status, [HomeworkStatus::Overdue, HomeworkStatus::Submitted, HomeworkStatus::Graded]);
+  let date = chrono::NaiveDate::from_ymd_opt(2020, 3, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+  assert_eq!(hs[0].status_at(date), HomeworkStatus::NotSubmitted);
+  let mut h = hs[0].clone();
+  h.deadline = beijing_now() + chrono::Duration::hours(1);
+  let hours = |x: u64| std::time::Duration::from_secs(x * 3600);
+  assert!(h.due_within(hours(24)) && !h.due_within(hours(1) / 2) && !hs[1].due_within(hours(240000)));
+  assert!(t.pending_homework(&semester()).await.unwrap().is_empty()); // all the deadlines in fixtures have passed
+  server.fail_requests("/b/wlxt/kczy/zy/student/index/zyListWj?wlkcid=c2");
+  assert!(t.pending_homework(&semester()).await.is_err());
+  t.submit_homework(&"s1".into(), "my answer".to_owned(), Some(("answer.txt", b"42".to_vec()))).await.unwrap();
+  let req = server.requests().pop().unwrap();
+  let body = String::from_utf8_lossy(&req.body);
+  assert!(body.contains("my answer") && body.contains("answer.txt") && body.contains("s1"));
+  // the method-style api
+  let c = t.course_list(&semester()).await.unwrap().remove(0);
+  let hs = c.homework_list(&t).await.unwrap();
+  hs[1].submit(&t, "again".to_owned(), None).await.unwrap();
+  assert!(String::from_utf8_lossy(&server.requests().pop().unwrap().body).contains(hs[1].student_homework_id.as_str()));
+  // replace, keep and remove the attachment of a submission, each verified with the detail page
+  assert_eq!(hs[1].submit_attachment.as_ref().unwrap().name, "pa2.zip");
+  let h = hs[1].resubmit(&t, "v2".to_owned(), AttachmentChange::Replace("v2.zip", b"v2".to_vec())).await.unwrap();
+  assert_eq!((h.submit_attachment.as_ref().unwrap().name.as_str(), h.submit_content.as_deref()), ("v2.zip", Some("v2")));
+  // it is submitted again after the deadline
+  assert!(h.status == HomeworkStatus::LateSubmitted && h.submit_time > hs[1].submit_time);
+  let h = t.resubmit_homework(&h, "v3".to_owned(), AttachmentChange::Keep).await.unwrap();
+  assert_eq!(h.submit_attachment.as_ref().unwrap().name, "v2.zip");
+  let h = t.delete_homework_attachment(&h).await.unwrap();
+  assert!(h.submit_attachment.is_none() && h.submit_content.as_deref() == Some("v3"));
+  assert!(t.homework_list(&course()).await.unwrap()[1].submit_attachment.is_none());
+  assert!(t.delete_homework_attachment(&hs[2]).await.is_err()); // the server ignores it
+  assert!(t.resubmit_homework(&hs[2], "changed".to_owned(), AttachmentChange::Keep).await.is_err());
+  let ds = c.discussion_list(&t).await.unwrap();
+  assert_eq!(c.discussion_replies(&t, &ds[0]).await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn discussions() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  let ds = t.discussion_list(&course()).await.unwrap();
+  assert_eq!(ds.len(), 2);
+  assert_eq!((ds[0].board_id.as_str(), ds[0].reply_count), ("b1", 2));
+  assert!(ds[1].last_replier_name.is_none() && ds[1].last_reply_time.is_none());
+  let rs = t.discussion_replies(&course(), &ds[0].id, &ds[0].board_id).await.unwrap();
+  assert_eq!(rs.len(), 3);
+  assert_eq!(rs[1].replies.len(), 2);
+  assert!(t.discussion_replies(&course(), &ds[1].id, &ds[1].board_id).await.unwrap().is_empty());
+  t.reply_discussion(&course(), &ds[0].id, "reply".to_owned(), Some(&"r1".into()), None).await.unwrap();
+  assert_eq!(rs.iter().flat_map(|r| r.replies.iter()).filter(|r| r.mine).map(|r| r.id.as_ref().unwrap().as_str()).collect::<Vec<_>>(), ["r3"]);
+  assert!(rs.iter().all(|r| !r.mine));
+  t.edit_discussion_reply(&course(), &ds[0].id, &"r3".into(), "收到！".to_owned(), None).await.unwrap();
+  let body = String::from_utf8_lossy(&server.requests().pop().unwrap().body).into_owned();
+  assert!(body.contains("r3") && body.contains("收到！"));
+  assert!(t.edit_discussion_reply(&course(), &ds[0].id, &"r1".into(), "c".to_owned(), None).await.is_err());
+  let (d, b, now) = (&ds[0].id, &ds[0].board_id, std::time::Duration::from_secs(0));
+  assert!(matches!(t.delete_discussion_reply(&course(), d, b, &"r1".into(), DELETE_DR_DEADLINE).await.unwrap(), DeleteOutcome::Failed));
+  // both of them time out, but only "r3" is really deleted
+  assert!(matches!(t.delete_discussion_reply(&course(), d, b, &"r3".into(), DELETE_DR_DEADLINE).await.unwrap(), DeleteOutcome::Confirmed));
+  assert!(!t.discussion_replies(&course(), d, b).await.unwrap()[1].replies.iter().any(|r| r.mine));
+  assert!(matches!(t.delete_discussion_reply(&course(), d, b, &"r2".into(), now).await.unwrap(), DeleteOutcome::Failed));
+  // every fetch of the replies fails until the deadline
+  server.fail_requests("/f/wlxt/bbs/bbs_tltb/student/viewTlById");
+  let outcome = t.delete_discussion_reply(&course(), d, b, &"r2".into(), std::time::Duration::from_secs(1)).await.unwrap();
+  assert!(matches!(outcome, DeleteOutcome::Assumed(Error::Server { status: 500, .. })));
+  assert!(server.requests().iter().filter(|r| r.path.starts_with("/f/wlxt/bbs/bbs_tltb/student/viewTlById")).count() >= 4);
+  let boards = t.discussion_boards(&course()).await.unwrap();
+  assert_eq!(boards.iter().map(|b| (b.id.as_str(), b.title.as_str())).collect::<Vec<_>>(), [("b1", "课程讨论区"), ("b2", "综合讨论区")]);
+  assert!(t.discussion_boards(&"c2".into()).await.unwrap().is_empty());
+  t.create_discussion(&course(), &boards[1].id, "组队".to_owned(), "<p>找队友</p>".to_owned(), Some(("a.txt", b"a".to_vec()))).await.unwrap();
+  let body = String::from_utf8_lossy(&server.requests().pop().unwrap().body).into_owned();
+  assert!(body.contains("b2") && body.contains("组队") && body.contains("找队友") && body.contains("a.txt"));
+  assert!(t.create_discussion(&course(), &"no-such-board".into(), "t".to_owned(), "c".to_owned(), None).await.is_err());
+  t.edit_discussion(&course(), &ds[1].id, &ds[1].board_id, "无人回复？".to_owned(), "c".to_owned(), None).await.unwrap();
+  assert!(t.edit_discussion(&course(), &ds[0].id, &ds[0].board_id, "t".to_owned(), "c".to_owned(), None).await.is_err());
+  t.delete_discussion(&course(), &ds[1].id).await.unwrap();
+  assert!(t.delete_discussion(&course(), &ds[0].id).await.is_err());
+}
+
+#[tokio::test]
+async fn questions() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  let qs = t.question_list(&course()).await.unwrap();
+  assert_eq!(qs.iter().map(|q| (q.id.as_str(), q.answered)).collect::<Vec<_>>(), [("q1", true), ("q2", false)]);
+  assert!(qs[1].last_reply_time.is_none());
+  let q = t.question_detail(&course(), &qs[0].id).await.unwrap();
+  assert_eq!((q.to_plain_text(&t.hosts).as_str(), q.answers[0].author.as_str()), ("PA1的测例是否公开？", "王生原"));
+  assert_eq!(q.answer(), Some("公开测例在实验框架中，另有隐藏测例。"));
+  assert_eq!(t.question_detail(&course(), &qs[1].id).await.unwrap().answer(), None);
+  assert!(t.question_list(&"c2".into()).await.unwrap().is_empty());
+  t.ask_question(&course(), "实验环境".to_owned(), "可以用Windows吗？".to_owned(), None).await.unwrap();
+  let body = String::from_utf8_lossy(&server.requests().pop().unwrap().body).into_owned();
+  assert!(body.contains("实验环境") && body.contains("可以用Windows吗？"));
+}
+
+#[tokio::test]
+async fn pagination() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  let busy = BUSY_COURSE.into();
+  let pages = |list: &str| server.requests().iter().filter(|r| r.path.contains(&format!("{}?wlkcid=busy&", list))).count();
+  let ds = t.discussion_list(&busy).await.unwrap();
+  assert_eq!((ds.len(), ds.last().unwrap().id.as_str()), (BUSY_COURSE_ITEMS, "busy-d449"));
+  assert_eq!(pages("kctlList"), 3);
+  // the stream stops fetching when the consumer stops polling
+  assert_eq!(t.files_stream(&busy).take(PAGE_SIZE + 1).try_collect::<Vec<_>>().await.unwrap().len(), PAGE_SIZE + 1);
+  assert_eq!(pages("kjxxbByWlkcidAndSizeForStudent"), 2);
+  server.fail_requests("/b/wlxt/bbs/bbs_tltb/student/kctlList?wlkcid=busy&size=200&currentPage=2");
+  let res = t.discussions_stream(&busy).collect::<Vec<_>>().await;
+  assert_eq!((res.len(), res[PAGE_SIZE - 1].is_ok(), res[PAGE_SIZE].is_err()), (PAGE_SIZE + 1, true, true));
+  assert!(t.discussion_list(&busy).await.is_err());
+  // the canned lists fit in one page
+  let ns = t.notifications_stream(&course()).try_collect::<Vec<_>>().await.unwrap();
+  assert_eq!(ns, t.notification_list(&course()).await.unwrap());
+  let hs = t.homework_stream(&course()).map_ok(|h| h.status).try_collect::<Vec<_>>().await.unwrap();
+  assert_eq!(hs, t.homework_list(&course()).await.unwrap().into_iter().map(|h| h.status).collect::<Vec<_>>());
+  assert_eq!(t.questions_stream(&course()).try_collect::<Vec<_>>().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn overview() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  let s = t.semester_overview(&semester(), &OverviewOptions { concurrency: 2, ..Default::default() }).await.unwrap();
+  assert!(server.max_in_flight() <= 2);
+  assert_eq!(s.errors().count(), 0);
+  assert_eq!(s.courses.len(), 2);
+  let c = &s.courses[0];
+  assert_eq!(c.course.time_location.len(), 2);
+  assert!(c.notifications.as_ref().unwrap()[1].attachment.is_some());
+  assert_eq!(c.files.as_ref().unwrap().len(), 2);
+  let ids = c.homework.as_ref().unwrap().iter().map(|h| h.id.as_str()).collect::<Vec<_>>();
+  assert_eq!(ids, ["h1", "h2", "h3"]);
+  assert_eq!(c.homework.as_ref().unwrap()[0].attachment.as_ref().unwrap().name, "pa1.pdf");
+  assert_eq!(c.discussions.as_ref().unwrap().len(), 2);
+  // a broken detail page fails its whole category, a broken list only fails its course
+  server.fail_requests("/f/wlxt/kczy/zy/student/viewCj?wlkcid=c1&zyid=h2");
+  server.fail_requests("/b/wlxt/bbs/bbs_tltb/student/kctlList?wlkcid=c2");
+  let s = t.semester_overview(&semester(), &OverviewOptions { files: false, ..Default::default() }).await.unwrap();
+  let errors = s.errors().map(|(c, category, _)| (c.id.as_str(), category)).collect::<Vec<_>>();
+  assert_eq!(errors, [("c1", Category::Homework), ("c2", Category::Discussions)]);
+  assert!(matches!(s.courses[0].error(Category::Homework), Some(Error::Server { .. })));
+  assert!(s.courses[0].homework.is_none() && s.courses[0].files.is_none());
+  assert_eq!(s.courses[0].discussions.as_ref().unwrap().len(), 2);
+  assert!(s.courses[1].discussions.is_none() && s.courses[1].homework.as_ref().unwrap().is_empty());
+  assert!(t.semester_overview(&"malformed".into(), &Default::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn watch() {
+  use std::time::Duration;
+  use thu_learn_helper::watch::*;
+  let server = TestServer::start();
+  let t = login(&server).await;
+  // c0 is gone, and c2 is new
+  let mut store = MemoryStore(serde_json::from_value(json!({ "courses": { "c0": {}, "c1": {
+    "notifications": ["n1"],
+    "files": { "f1": ["2020-02-17T08:00:00", 1] },
+    "homework": { "h1": ["2020-02-01T00:00:00", false], "h3": ["2020-02-27T23:59:00", false] },
+    "discussions": { "d1": 1 },
+  } } })).unwrap());
+  server.fail_requests("/b/wlxt/bbs/bbs_tltb/student/kctlList?wlkcid=c2");
+  let events = t.watch(&semester(), Duration::from_secs(3600), &Default::default(), &mut store).take(9).collect::<Vec<_>>().await;
+  let events = events.iter().map(|e| match e {
+    Event::NewNotification(c, x) => format!("{} notification {}", c.id, x.id),
+    Event::NewFile(c, x) => format!("{} file {}", c.id, x.id),
+    Event::FileUpdated(c, x) => format!("{} updated {}", c.id, x.id),
+    Event::HomeworkAssigned(c, x) => format!("{} assigned {}", c.id, x.id),
+    Event::DeadlineChanged(c, x, old) => format!("{} deadline {} {}", c.id, x.id, old),
+    Event::HomeworkGraded(c, x) => format!("{} graded {}", c.id, x.id),
+    Event::NewDiscussionReply(c, x) => format!("{} reply {}", c.id, x.id),
+    Event::Failed(x, _) => format!("failed {:?}", x.as_ref().map(|(c, category)| (c.id.as_str(), category))),
+  }).collect::<Vec<_>>();
+  assert_eq!(events, ["c1 notification n2", "c1 updated f1", "c1 file f2", "c1 deadline h1 2020-02-01 00:00:00", "c1 assigned h2",
+    "c1 deadline h3 2020-02-27 23:59:00", "c1 graded h3", "c1 reply d1", "failed Some((\"c2\", Discussions))"]);
+  assert_eq!(store.0.courses.keys().map(|x| x.as_str()).collect::<Vec<_>>(), ["c1", "c2"]);
+  assert!(store.0.courses[&"c2".into()].discussions.is_none() && store.0.courses[&"c2".into()].files.is_some());
+  // a restarted watch doesn't emit the old events again
+  let path = std::env::temp_dir().join(format!("thu-learn-helper-watch-{}.json", std::process::id()));
+  let mut file = FileStore(path.clone());
+  file.save(&store.0).unwrap();
+  assert_eq!(file.load().unwrap(), store.0);
+  let event = Box::pin(t.watch(&semester(), Duration::from_secs(3600), &Default::default(), file)).next().await.unwrap();
+  assert!(matches!(event, Event::Failed(Some((_, Category::Discussions)), _)));
+  std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn ical() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  let mut s = t.semester_overview(&semester(), &Default::default()).await.unwrap();
+  s.courses[0].homework.as_mut().unwrap()[2].grade_content = Some("<p>很<b>好</b></p>".to_owned());
+  let ics = Calendar::new(&t.hosts).snapshot(&s, chrono::NaiveDate::from_ymd_opt(2020, 2, 19).unwrap()).to_string();
+  assert!(ics.starts_with("BEGIN:VCALENDAR\r\n") && ics.ends_with("END:VCALENDAR\r\n"));
+  assert!(ics.split("\r\n").all(|l| l.len() <= 75));
+  let unfolded = ics.replace("\r\n ", "");
+  // monday of the first week is 2020-02-17, "星期三第4节(单周)" starts at 15:20 in Beijing time, its uid has the weekday, periods and weeks
+  let event = unfolded.split("BEGIN:VEVENT").find(|x| x.contains("UID:course-c1-3-4-4-1-2-8-")).unwrap();
+  assert!(event.contains("DTSTART:20200219T072000Z") && event.contains("RRULE:FREQ=WEEKLY;INTERVAL=2;COUNT=8") && event.contains("LOCATION:六教6A017"));
+  // reordered or repeated slots keep the same events
+  let uids = |c: &Course| {
+    let ics = Calendar::new(&t.hosts).timetable(c, chrono::NaiveDate::from_ymd_opt(2020, 2, 19).unwrap()).to_string();
+    ics.split("\r\n").filter(|l| l.starts_with("UID:")).map(str::to_owned).collect::<Vec<_>>()
+  };
+  let mut c = s.courses[0].course.clone();
+  let before = uids(&c);
+  c.time_location.reverse();
+  c.time_location.push(c.time_location[0].clone());
+  let mut after = uids(&c);
+  after.reverse();
+  assert_eq!((before.len(), after), (2, before));
+  assert_eq!(unfolded.matches("BEGIN:VTODO").count(), 3);
+  let todo = unfolded.split("BEGIN:VTODO").find(|x| x.contains("UID:homework-c1-h3@")).unwrap();
+  assert!(todo.contains("STATUS:COMPLETED") && todo.contains("成绩: 95") && todo.contains("评语: 很好") && todo.contains(&format!("URL:{}/f/wlxt/kczy/zy/student/viewCj?", t.hosts.learn)));
+  assert!(unfolded.split("BEGIN:VTODO").any(|x| x.contains("UID:homework-c1-h1@") && x.contains("STATUS:NEEDS-ACTION")));
+}
+
+#[tokio::test]
+async fn logout() {
+  let server = TestServer::start();
+  let t = login(&server).await;
+  let t1 = login(&server).await;
+  t.logout().await.unwrap();
+  assert!(t1.semester_id_list().await.is_ok()); // another session is unaffected
+}
+
+#[test]
+fn blocking() {
+  use thu_learn_helper::blocking::LearnHelper;
+  let server = TestServer::start();
+  assert!(LearnHelper::login_to(server.hosts(), USERNAME, "wrong password").is_err());
+  let t = LearnHelper::login_to(server.hosts(), USERNAME, PASSWORD).unwrap();
+  assert_eq!(t.semester_id_list().unwrap(), ["2019-2020-2", "2019-2020-1"]);
+  assert_eq!(t.current_semester().unwrap().end_date.to_string(), "2020-06-21");
+  let c = t.course_list(&semester()).unwrap().remove(0);
+  assert_eq!(c.time_location.len(), 2);
+  assert_eq!(c.homework_list_blocking(&t).unwrap().len(), 3);
+  assert_eq!(c.discussion_replies_blocking(&t, &c.discussion_list_blocking(&t).unwrap()[0]).unwrap().len(), 3);
+  assert!(t.notification_list(&course()).unwrap()[1].attachment.is_some());
+  let fs = t.file_list(&course()).unwrap();
+  let mut out = Vec::new();
+  assert_eq!(t.download_file(&fs[1], 0, &mut out, |_| {}).unwrap().size, 20480);
+  let mut part = out[..20000].to_vec();
+  assert_eq!(t.download_file(&fs[1], 20000, &mut part, |_| {}).unwrap().size, 20480);
+  assert_eq!(part, out);
+  let hs = t.homework_list(&course()).unwrap();
+  assert_eq!(hs[1].status, HomeworkStatus::Submitted);
+  assert_eq!(t.resubmit_homework(&hs[0], "a".to_owned(), AttachmentChange::Replace("a.txt", b"a".to_vec())).unwrap().detail.submit_attachment.unwrap().name, "a.txt");
+  assert!(t.delete_homework_attachment(&hs[1]).unwrap().submit_attachment.is_none());
+  assert!(t.pending_homework(&semester()).unwrap().is_empty());
+  assert_eq!(t.mark_all_read(&course()).unwrap(), 2);
+  t.submit_homework(&"s1".into(), "my answer".to_owned(), None).unwrap();
+  let ds = t.discussion_list(&course()).unwrap();
+  assert_eq!(t.discussions_stream(&BUSY_COURSE.into()).filter(Result::is_ok).count(), BUSY_COURSE_ITEMS);
+  assert_eq!(t.homework_stream(&course()).count(), 3);
+  assert_eq!(t.discussion_replies(&course(), &ds[0].id, &ds[0].board_id).unwrap().len(), 3);
+  t.reply_discussion(&course(), &ds[0].id, "reply".to_owned(), None, None).unwrap();
+  assert!(t.question_detail(&course(), &t.question_list(&course()).unwrap()[0].id).unwrap().answer().is_some());
+  t.ask_question(&course(), "title".to_owned(), "content".to_owned(), Some(("a.txt", b"a".to_vec()))).unwrap();
+  t.edit_discussion_reply(&course(), &ds[0].id, &"r3".into(), "content".to_owned(), None).unwrap();
+  assert!(matches!(t.delete_discussion_reply(&course(), &ds[0].id, &ds[0].board_id, &"r3".into(), DELETE_DR_DEADLINE).unwrap(), DeleteOutcome::Confirmed));
+  let board = &t.discussion_boards(&course()).unwrap()[0].id;
+  t.create_discussion(&course(), board, "title".to_owned(), "content".to_owned(), None).unwrap();
+  t.edit_discussion(&course(), &ds[1].id, board, "title".to_owned(), "content".to_owned(), None).unwrap();
+  t.delete_discussion(&course(), &ds[1].id).unwrap();
+  server.expire_sessions();
+  match t.file_list(&course()) { Err(Error::SessionExpired) => {}, _ => panic!() }
+  let t = LearnHelper::login_with(server.hosts(), Credentials::Stored { username: USERNAME.to_owned(), password: PASSWORD.to_owned() }).unwrap();
+  server.expire_sessions();
+  assert_eq!(t.file_list(&course()).unwrap().len(), 2);
+  let root = std::env::temp_dir().join(format!("thu-learn-helper-blocking-sync-{}", std::process::id()));
+  assert_eq!(t.sync_semester(&root, &semester()).unwrap().added.len(), 7);
+  assert!(t.sync_semester(&root, &semester()).unwrap().added.is_empty());
+  std::fs::remove_dir_all(&root).unwrap();
+  let mut store = thu_learn_helper::watch::MemoryStore::default();
+  store.0.courses.insert(course(), thu_learn_helper::watch::CourseState { notifications: Some(std::iter::once("n1".into()).collect()), ..Default::default() });
+  // only the notifications are watched, the other categories are not fetched
+  let options = thu_learn_helper::types::OverviewOptions { files: false, homework: false, discussions: false, ..Default::default() };
+  let event = t.watch(&semester(), std::time::Duration::from_secs(0), &options, &mut store).next();
+  assert!(matches!(event, Some(thu_learn_helper::watch::Event::NewNotification(_, n)) if n.id == "n2"));
+  assert!(store.0.courses[&course()].files.is_none() && store.0.courses[&course()].homework.is_none());
+  server.fail_requests("/b/wlxt/kj/wlkc_kjxxb/student/kjxxbByWlkcidAndSizeForStudent?wlkcid=c1");
+  let s = t.semester_overview(&semester(), &Default::default()).unwrap();
+  assert!(s.courses[0].files.is_none() && s.courses[0].error(thu_learn_helper::types::Category::Files).is_some());
+  assert_eq!(s.courses[0].homework.as_ref().unwrap()[2].grade, Some(95.0));
+  let t = LearnHelper::restore_session(t.save_session()).unwrap();
+  assert!(t.is_session_valid().unwrap());
+  t.logout().unwrap();
+}